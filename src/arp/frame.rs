@@ -0,0 +1,277 @@
+use std::net::Ipv4Addr;
+
+use pnet::packet::arp::{
+    ArpHardwareType, ArpHardwareTypes, ArpOperation, ArpOperations, ArpPacket, MutableArpPacket,
+};
+use pnet::packet::ethernet::{EtherType, EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::util::MacAddr;
+
+use super::{ARP_OFFSET, ARP_SIZE, ETHERNET_SIZE};
+
+/// A builder for Ethernet frames carrying an ARP packet.
+///
+/// Every header field can be set, which makes it possible to craft non-standard frames. The defaults describe a
+/// broadcast Ethernet/IPv4 ARP request with all addresses zeroed.
+///
+/// Note: The address fields are always laid out as 6-byte MAC and 4-byte IPv4 addresses. Changing the hardware or
+/// protocol address lengths only changes the values advertised in the header.
+#[derive(Debug, Clone, Copy)]
+pub struct ArpFrameBuilder {
+    ethernet_destination: MacAddr,
+    ethernet_source: MacAddr,
+    ethertype: EtherType,
+    hardware_type: ArpHardwareType,
+    protocol_type: EtherType,
+    hw_addr_len: u8,
+    proto_addr_len: u8,
+    operation: ArpOperation,
+    sender_hw_addr: MacAddr,
+    sender_proto_addr: Ipv4Addr,
+    target_hw_addr: MacAddr,
+    target_proto_addr: Ipv4Addr,
+}
+
+impl Default for ArpFrameBuilder {
+    fn default() -> Self {
+        Self {
+            ethernet_destination: MacAddr::broadcast(),
+            ethernet_source: MacAddr::zero(),
+            ethertype: EtherTypes::Arp,
+            hardware_type: ArpHardwareTypes::Ethernet,
+            protocol_type: EtherTypes::Ipv4,
+            hw_addr_len: 6,
+            proto_addr_len: 4,
+            operation: ArpOperations::Request,
+            sender_hw_addr: MacAddr::zero(),
+            sender_proto_addr: Ipv4Addr::UNSPECIFIED,
+            target_hw_addr: MacAddr::zero(),
+            target_proto_addr: Ipv4Addr::UNSPECIFIED,
+        }
+    }
+}
+
+impl ArpFrameBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the destination MAC address of the Ethernet header.
+    pub fn ethernet_destination(mut self, addr: MacAddr) -> Self {
+        self.ethernet_destination = addr;
+        self
+    }
+
+    /// Sets the source MAC address of the Ethernet header.
+    pub fn ethernet_source(mut self, addr: MacAddr) -> Self {
+        self.ethernet_source = addr;
+        self
+    }
+
+    /// Sets the EtherType of the Ethernet header.
+    pub fn ethertype(mut self, ethertype: EtherType) -> Self {
+        self.ethertype = ethertype;
+        self
+    }
+
+    /// Sets the hardware type (HTYPE) of the ARP packet.
+    pub fn hardware_type(mut self, hardware_type: ArpHardwareType) -> Self {
+        self.hardware_type = hardware_type;
+        self
+    }
+
+    /// Sets the protocol type (PTYPE) of the ARP packet.
+    pub fn protocol_type(mut self, protocol_type: EtherType) -> Self {
+        self.protocol_type = protocol_type;
+        self
+    }
+
+    /// Sets the hardware address length (HLEN) of the ARP packet.
+    pub fn hw_addr_len(mut self, len: u8) -> Self {
+        self.hw_addr_len = len;
+        self
+    }
+
+    /// Sets the protocol address length (PLEN) of the ARP packet.
+    pub fn proto_addr_len(mut self, len: u8) -> Self {
+        self.proto_addr_len = len;
+        self
+    }
+
+    /// Sets the operation (OPER) of the ARP packet.
+    pub fn operation(mut self, operation: ArpOperation) -> Self {
+        self.operation = operation;
+        self
+    }
+
+    pub fn sender_hw_addr(mut self, addr: MacAddr) -> Self {
+        self.sender_hw_addr = addr;
+        self
+    }
+
+    pub fn sender_proto_addr(mut self, addr: Ipv4Addr) -> Self {
+        self.sender_proto_addr = addr;
+        self
+    }
+
+    pub fn target_hw_addr(mut self, addr: MacAddr) -> Self {
+        self.target_hw_addr = addr;
+        self
+    }
+
+    pub fn target_proto_addr(mut self, addr: Ipv4Addr) -> Self {
+        self.target_proto_addr = addr;
+        self
+    }
+
+    /// Serializes the frame into a buffer ready for transmission.
+    pub fn build(&self) -> Vec<u8> {
+        let mut packet_buf = vec![0u8; ETHERNET_SIZE + ARP_SIZE];
+
+        let mut eth_layer = MutableEthernetPacket::new(&mut packet_buf).unwrap();
+
+        eth_layer.set_destination(self.ethernet_destination);
+        eth_layer.set_source(self.ethernet_source);
+        eth_layer.set_ethertype(self.ethertype);
+
+        let mut arp_layer = MutableArpPacket::new(&mut packet_buf[ARP_OFFSET..]).unwrap();
+
+        arp_layer.set_hardware_type(self.hardware_type);
+        arp_layer.set_protocol_type(self.protocol_type);
+        arp_layer.set_hw_addr_len(self.hw_addr_len);
+        arp_layer.set_proto_addr_len(self.proto_addr_len);
+        arp_layer.set_operation(self.operation);
+        arp_layer.set_sender_hw_addr(self.sender_hw_addr);
+        arp_layer.set_sender_proto_addr(self.sender_proto_addr);
+        arp_layer.set_target_hw_addr(self.target_hw_addr);
+        arp_layer.set_target_proto_addr(self.target_proto_addr);
+
+        packet_buf
+    }
+}
+
+/// A received Ethernet frame which has been validated to carry an Ethernet/IPv4 ARP request or reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArpFrame {
+    pub ethernet_destination: MacAddr,
+    pub ethernet_source: MacAddr,
+    pub operation: ArpOperation,
+    pub sender_hw_addr: MacAddr,
+    pub sender_proto_addr: Ipv4Addr,
+    pub target_hw_addr: MacAddr,
+    pub target_proto_addr: Ipv4Addr,
+}
+
+impl ArpFrame {
+    /// Parses a raw Ethernet frame.
+    ///
+    /// Returns `None` if the frame is too short, does not carry ARP, uses a hardware or protocol type other than
+    /// Ethernet and IPv4, advertises the wrong address lengths or has an opcode other than request or reply.
+    /// Frames whose Ethernet source is `local_hw_addr` are rejected as well, since they were transmitted by us.
+    pub fn parse(buf: &[u8], local_hw_addr: Option<MacAddr>) -> Option<Self> {
+        if buf.len() < ETHERNET_SIZE + ARP_SIZE {
+            return None;
+        }
+
+        let eth_layer = EthernetPacket::new(buf)?;
+
+        if eth_layer.get_ethertype() != EtherTypes::Arp
+            || Some(eth_layer.get_source()) == local_hw_addr
+        {
+            return None;
+        }
+
+        let arp_layer = ArpPacket::new(&buf[ARP_OFFSET..])?;
+
+        if arp_layer.get_hardware_type() != ArpHardwareTypes::Ethernet
+            || arp_layer.get_protocol_type() != EtherTypes::Ipv4
+            || arp_layer.get_hw_addr_len() != 6
+            || arp_layer.get_proto_addr_len() != 4
+        {
+            return None;
+        }
+
+        let operation = arp_layer.get_operation();
+        if operation != ArpOperations::Request && operation != ArpOperations::Reply {
+            return None;
+        }
+
+        Some(Self {
+            ethernet_destination: eth_layer.get_destination(),
+            ethernet_source: eth_layer.get_source(),
+            operation,
+            sender_hw_addr: arp_layer.get_sender_hw_addr(),
+            sender_proto_addr: arp_layer.get_sender_proto_addr(),
+            target_hw_addr: arp_layer.get_target_hw_addr(),
+            target_proto_addr: arp_layer.get_target_proto_addr(),
+        })
+    }
+
+    pub fn is_request(&self) -> bool {
+        self.operation == ArpOperations::Request
+    }
+
+    pub fn is_reply(&self) -> bool {
+        self.operation == ArpOperations::Reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCAL: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 0x01);
+    const REMOTE: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 0x02);
+
+    fn reply() -> ArpFrameBuilder {
+        ArpFrameBuilder::new()
+            .ethernet_destination(LOCAL)
+            .ethernet_source(REMOTE)
+            .operation(ArpOperations::Reply)
+            .sender_hw_addr(REMOTE)
+            .sender_proto_addr(Ipv4Addr::new(10, 0, 0, 2))
+            .target_hw_addr(LOCAL)
+            .target_proto_addr(Ipv4Addr::new(10, 0, 0, 1))
+    }
+
+    #[test]
+    fn parses_reply() {
+        let frame = ArpFrame::parse(&reply().build(), Some(LOCAL)).unwrap();
+
+        assert!(frame.is_reply());
+        assert_eq!(frame.ethernet_source, REMOTE);
+        assert_eq!(frame.sender_hw_addr, REMOTE);
+        assert_eq!(frame.sender_proto_addr, Ipv4Addr::new(10, 0, 0, 2));
+        assert_eq!(frame.target_proto_addr, Ipv4Addr::new(10, 0, 0, 1));
+    }
+
+    #[test]
+    fn rejects_short_frame() {
+        let buf = reply().build();
+
+        assert_eq!(ArpFrame::parse(&buf[..buf.len() - 1], None), None);
+    }
+
+    #[test]
+    fn rejects_own_frame() {
+        let buf = reply().ethernet_source(LOCAL).build();
+
+        assert_eq!(ArpFrame::parse(&buf, Some(LOCAL)), None);
+        assert!(ArpFrame::parse(&buf, None).is_some());
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let malformed = [
+            reply().ethertype(EtherTypes::Ipv4),
+            reply().hardware_type(ArpHardwareType::new(6)),
+            reply().protocol_type(EtherTypes::Ipv6),
+            reply().hw_addr_len(8),
+            reply().proto_addr_len(16),
+            reply().operation(ArpOperation::new(3)),
+        ];
+
+        for builder in malformed {
+            assert_eq!(ArpFrame::parse(&builder.build(), None), None, "{:?}", builder);
+        }
+    }
+}
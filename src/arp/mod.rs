@@ -1,17 +1,45 @@
 use std::net::Ipv4Addr;
-use std::thread;
 use std::time::Duration;
 
 use nic::{InterfaceError, NetworkInterface};
 use pnet::util::MacAddr;
 
-use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, ArpPacket, MutableArpPacket};
-use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
-
-use crate::utils::run_with_timeout;
+use pnet::packet::arp::ArpPacket;
+use pnet::packet::ethernet::EthernetPacket;
 
 pub mod nic;
 
+mod frame;
+pub use frame::{ArpFrame, ArpFrameBuilder};
+
+mod host;
+pub use host::{Host, HostsFileError};
+
+pub mod targets;
+pub use targets::TargetSet;
+
+mod session;
+pub use session::{ArpSession, CancelHandle, ScanOptions};
+
+mod listener;
+pub use listener::ArpListener;
+
+pub mod impersonate;
+
+mod mitm;
+pub use mitm::{MitmPair, Peer};
+
+pub mod heal;
+
+pub mod relay;
+pub use relay::Relay;
+
+pub mod probe;
+pub use probe::ProbeOutcome;
+
+pub mod oui;
+pub use oui::{MacFlag, OuiDatabase};
+
 const ETHERNET_SIZE: usize = EthernetPacket::minimum_packet_size();
 const ARP_OFFSET: usize = ETHERNET_SIZE;
 const ARP_SIZE: usize = ArpPacket::minimum_packet_size();
@@ -24,69 +52,23 @@ const ARP_SIZE: usize = ArpPacket::minimum_packet_size();
 /// * `target_proto_addr` - The destination IP address of the ARP request.
 /// * `sender_mac_addr` - An optional MAC address to use as the source MAC for the ARP request. If `None` is specified, then the interface's MAC address is used.
 /// * `sender_proto_addr` - An optional IP address to use as the source IP for the ARP request. If `None` is specified, then the interface's IP address is used.
+/// * `eth_dest` - An optional Ethernet destination for the frame. If `None` is specified, then the request is broadcast.
 ///
 /// Note: This function does not await a response. To resolve an IP address, use resolve_ip.
+/// It opens a new datalink channel for every call; use an `ArpSession` when sending more than a handful of frames.
 pub fn send_arp_request(
     interface: &NetworkInterface,
     target_proto_addr: Ipv4Addr,
     sender_hw_addr: Option<MacAddr>,
     sender_proto_addr: Option<Ipv4Addr>,
+    eth_dest: Option<MacAddr>,
 ) -> Result<(), InterfaceError> {
-    // Process the source MAC and IP addresses for spoofing
-    let source_mac = if sender_hw_addr.is_some() {
-        sender_hw_addr.unwrap()
-    } else {
-        if interface.mac().is_some() {
-            interface.mac().unwrap()
-        } else {
-            return Err(InterfaceError::MissingMAC);
-        }
-    };
-
-    let source_ip = if sender_proto_addr.is_some() {
-        sender_proto_addr.unwrap()
-    } else {
-        if interface.ipv4_address().is_some() {
-            interface.ipv4_address().unwrap()
-        } else {
-            return Err(InterfaceError::MissingIP);
-        }
-    };
-
-    let mut packet_buf = [0u8; ETHERNET_SIZE + ARP_SIZE];
-
-    let mut eth_layer = MutableEthernetPacket::new(&mut packet_buf).unwrap();
-
-    eth_layer.set_destination(MacAddr::broadcast());
-    eth_layer.set_source(source_mac);
-    eth_layer.set_ethertype(EtherTypes::Arp);
-
-    let mut arp_layer = MutableArpPacket::new(&mut packet_buf[ARP_OFFSET..]).unwrap();
-
-    arp_layer.set_hardware_type(ArpHardwareTypes::Ethernet);
-    arp_layer.set_protocol_type(EtherTypes::Ipv4);
-    arp_layer.set_hw_addr_len(6);
-    arp_layer.set_proto_addr_len(4);
-    arp_layer.set_operation(ArpOperations::Request);
-    arp_layer.set_sender_hw_addr(source_mac);
-    arp_layer.set_sender_proto_addr(source_ip);
-    arp_layer.set_target_hw_addr(MacAddr::zero());
-    arp_layer.set_target_proto_addr(target_proto_addr);
-
-    use pnet::datalink::*;
-
-    let mut tx = match channel(&interface.clone().into(), Default::default()) {
-        Ok(Channel::Ethernet(tx, _)) => tx,
-        Ok(_) => panic!("Unhandled channel type"),
-        Err(e) => panic!(
-            "An error occurred when creating the datalink channel: {}",
-            e
-        ),
-    };
-
-    tx.send_to(&packet_buf, None);
-
-    Ok(())
+    ArpSession::new(interface)?.send_request(
+        target_proto_addr,
+        sender_hw_addr,
+        sender_proto_addr,
+        eth_dest,
+    )
 }
 
 /// Sends an ARP response packet.
@@ -94,184 +76,83 @@ pub fn send_arp_request(
 /// # Arguments
 ///
 /// * `interface` - The network interface to use for the transmission.
+/// * `eth_dest` - An optional Ethernet destination for the frame. If `None` is specified, then the reply is unicast to `target_hw_addr`.
+///
+/// Note: This function opens a new datalink channel for every call; use an `ArpSession` when sending more than a handful of frames.
 pub fn send_arp_reply(
     interface: &NetworkInterface,
     target_hw_addr: MacAddr,
     target_proto_addr: Ipv4Addr,
     sender_hw_addr: Option<MacAddr>,
     sender_proto_addr: Option<Ipv4Addr>,
+    eth_dest: Option<MacAddr>,
 ) -> Result<(), InterfaceError> {
-    // Process the source MAC and IP addresses for spoofing
-    let source_mac = if sender_hw_addr.is_some() {
-        sender_hw_addr.unwrap()
-    } else {
-        if interface.mac().is_some() {
-            interface.mac().unwrap()
-        } else {
-            return Err(InterfaceError::MissingMAC);
-        }
-    };
-
-    let source_ip = if sender_proto_addr.is_some() {
-        sender_proto_addr.unwrap()
-    } else {
-        if interface.ipv4_address().is_some() {
-            interface.ipv4_address().unwrap()
-        } else {
-            return Err(InterfaceError::MissingIP);
-        }
-    };
-
-    let mut packet_buf = [0u8; ETHERNET_SIZE + ARP_SIZE];
-
-    let mut eth_layer = MutableEthernetPacket::new(&mut packet_buf).unwrap();
-
-    eth_layer.set_destination(MacAddr::broadcast());
-    eth_layer.set_source(source_mac);
-    eth_layer.set_ethertype(EtherTypes::Arp);
-
-    let mut arp_layer = MutableArpPacket::new(&mut packet_buf[ARP_OFFSET..]).unwrap();
-
-    arp_layer.set_hardware_type(ArpHardwareTypes::Ethernet);
-    arp_layer.set_protocol_type(EtherTypes::Ipv4);
-    arp_layer.set_hw_addr_len(6);
-    arp_layer.set_proto_addr_len(4);
-    arp_layer.set_operation(ArpOperations::Reply);
-    arp_layer.set_sender_hw_addr(source_mac);
-    arp_layer.set_sender_proto_addr(source_ip);
-    arp_layer.set_target_hw_addr(target_hw_addr);
-    arp_layer.set_target_proto_addr(target_proto_addr);
-
-    use pnet::datalink::*;
+    ArpSession::new(interface)?.send_reply(
+        target_hw_addr,
+        target_proto_addr,
+        sender_hw_addr,
+        sender_proto_addr,
+        eth_dest,
+    )
+}
 
-    let mut tx = match channel(&interface.clone().into(), Default::default()) {
-        Ok(Channel::Ethernet(tx, _)) => tx,
-        Ok(_) => panic!("Unhandled channel type"),
-        Err(e) => panic!(
-            "An error occurred when creating the datalink channel: {}",
-            e
-        ),
-    };
+/// Sends a gratuitous ARP announcement of the given IP address.
+///
+/// # Arguments
+///
+/// * `interface` - The network interface to use for the transmission.
+/// * `proto_addr` - The IP address to announce.
+/// * `hw_addr` - An optional MAC address to announce for the IP. If `None` is specified, then the interface's MAC address is used.
+/// * `style` - Whether to send the announcement as an ARP request or as an ARP reply.
+///
+/// Note: This function opens a new datalink channel for every call; use an `ArpSession` when sending more than a handful of frames.
+pub fn send_gratuitous_arp(
+    interface: &NetworkInterface,
+    proto_addr: Ipv4Addr,
+    hw_addr: Option<MacAddr>,
+    style: AnnouncementStyle,
+) -> Result<(), InterfaceError> {
+    let mut session = ArpSession::new(interface)?;
 
-    tx.send_to(&packet_buf, None);
+    match style {
+        AnnouncementStyle::Request => session.send_gratuitous_request(proto_addr, hw_addr),
+        AnnouncementStyle::Reply => session.send_gratuitous_reply(proto_addr, hw_addr),
+    }
+}
 
-    Ok(())
+/// The form in which a gratuitous ARP announcement is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnouncementStyle {
+    /// An ARP request whose sender and target IP addresses are the announced address.
+    Request,
+    /// An ARP reply whose sender and target addresses both describe the announced host.
+    Reply,
 }
 
 /// Attempts to find the MAC associated with the given IP address
+///
+/// Note: The datalink channel is closed again before this function returns.
 pub fn resolve_mac(
     interface: NetworkInterface,
     dest_ip: Ipv4Addr,
     timeout: Duration,
 ) -> Result<Option<MacAddr>, InterfaceError> {
-    use pnet::datalink::*;
-
-    let mut rx = match channel(&interface.clone().into(), Default::default()) {
-        Ok(Channel::Ethernet(_, rx)) => rx,
-        Ok(_) => panic!("Unhandled channel type"),
-        Err(e) => panic!(
-            "An error occurred when creating the datalink channel: {}",
-            e
-        ),
-    };
-    send_arp_request(
-        &interface,
-        dest_ip,
-        interface.mac(),
-        interface.ipv4_address(),
-    )?;
-
-    match run_with_timeout(
-        move || loop {
-            let buf = rx.next().unwrap();
-
-            if buf.len() < ETHERNET_SIZE + ARP_SIZE {
-                continue;
-            }
-
-            let arp_layer = ArpPacket::new(&buf[ARP_OFFSET..]).unwrap();
-
-            if arp_layer.get_sender_proto_addr() == dest_ip
-                && arp_layer.get_target_hw_addr() == interface.mac().unwrap()
-            {
-                return arp_layer.get_sender_hw_addr();
-            }
-        },
-        timeout,
-    ) {
-        Ok(addr) => Ok(Some(addr)),
-        Err(_) => Ok(None),
-    }
+    ArpSession::new(&interface)?.resolve(dest_ip, timeout)
 }
 
+/// Performs an ARP scan of the interface's network, skipping the network and broadcast addresses and the
+/// interface's own IP. See `ArpSession::scan`.
+///
+/// Note: The datalink channel is closed again before this function returns.
 pub fn arp_scan(
     interface: &NetworkInterface,
     timeout: Duration,
-) -> Result<Vec<(Ipv4Addr, MacAddr)>, InterfaceError> {
-    let Some(network) = interface.network() else {
-        return Err(InterfaceError::MissingIP);
-    };
-
-    let Some(interface_mac) = interface.mac() else {
-        return Err(InterfaceError::MissingMAC);
-    };
-
-    use pnet::datalink::*;
-
-    let mut packet_rx = match channel(&interface.clone().into(), Default::default()) {
-        Ok(Channel::Ethernet(_, rx)) => rx,
-        Ok(_) => panic!("Unhandled channel type"),
-        Err(e) => panic!(
-            "An error occurred when creating the datalink channel: {}",
-            e
-        ),
-    };
-
-    let (hosts_tx, mut hosts_rx) = tokio::sync::mpsc::channel::<(Ipv4Addr, MacAddr)>(64);
-
-    run_with_timeout(
-        {
-            let interface = interface.clone();
-            let hosts_tx = hosts_tx.downgrade();
-
-            move || {
-                // Start the receiver thread
-                let listener = thread::spawn(move || loop {
-                    let buf = packet_rx.next().unwrap();
-
-                    if buf.len() < ETHERNET_SIZE + ARP_SIZE {
-                        continue;
-                    }
-
-                    let arp_layer = ArpPacket::new(&buf[ARP_OFFSET..]).unwrap();
-
-                    if arp_layer.get_target_hw_addr() == interface_mac {
-                        hosts_tx.upgrade().unwrap()
-                            .blocking_send((
-                                arp_layer.get_sender_proto_addr(),
-                                arp_layer.get_sender_hw_addr(),
-                            ))
-                            .unwrap();
-                    }
-                });
-
-                for ip in network.into_iter() {
-                    send_arp_request(&interface, ip, None, None).unwrap();
-                }
-
-                listener.join().unwrap();
-            }
-        },
+) -> Result<Vec<Host>, InterfaceError> {
+    let targets = TargetSet::for_interface(interface)?;
+    let options = ScanOptions {
         timeout,
-    )
-    .ok();
-
-    drop(hosts_tx);
-    let mut hosts: Vec<(Ipv4Addr, MacAddr)> = Vec::new();
-
-    while let Some(host) = hosts_rx.blocking_recv() {
-        hosts.push(host);
-    }
+        ..Default::default()
+    };
 
-    Ok(hosts)
+    ArpSession::new(interface)?.scan(&targets, &options)
 }
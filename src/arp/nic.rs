@@ -1,4 +1,6 @@
 use core::fmt;
+use std::fs;
+use std::io;
 use std::net::Ipv4Addr;
 
 #[derive(Debug, Clone)]
@@ -21,22 +23,43 @@ impl NetworkInterface {
     }
 
     pub fn ipv4_address(&self) -> Option<Ipv4Addr> {
-        match &self.ipv4_net {
-            Some(network) => { Some(network.ip()) },
-            None => None
-        }
+        self.ipv4_net.as_ref().map(|network| network.ip())
     }
 
     pub fn network_address(&self) -> Option<Ipv4Addr> {
-        match &self.ipv4_net {
-            Some(network) => Some(network.network()),
-            None => None
-        }
+        self.ipv4_net.as_ref().map(|network| network.network())
     }
 
     pub fn network(&self) -> Option<pnet::ipnetwork::Ipv4Network> {
         self.ipv4_net
     }
+
+    /// Looks up the default gateway of the interface in the kernel's routing table, `/proc/net/route`.
+    pub fn default_gateway(&self) -> Result<Option<Ipv4Addr>, io::Error> {
+        // The flag marking routes which go through a gateway
+        const RTF_GATEWAY: u16 = 0x2;
+
+        let routes = fs::read_to_string("/proc/net/route")?;
+
+        // Iface, Destination, Gateway, Flags, ...
+        let gateway = routes.lines().skip(1).find_map(|route| {
+            let fields: Vec<&str> = route.split_whitespace().collect();
+            let [iface, destination, gateway, flags, ..] = fields.as_slice() else {
+                return None;
+            };
+
+            let flags = u16::from_str_radix(flags, 16).ok()?;
+            if *iface != self.name() || *destination != "00000000" || flags & RTF_GATEWAY == 0 {
+                return None;
+            }
+
+            // Addresses are printed as hexadecimal numbers in the host's byte order
+            let gateway = u32::from_str_radix(gateway, 16).ok()?;
+            Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+        });
+
+        Ok(gateway)
+    }
 }
 
 
@@ -59,9 +82,9 @@ impl From<pnet::datalink::NetworkInterface> for NetworkInterface {
     }
 }
 
-impl Into<pnet::datalink::NetworkInterface> for NetworkInterface {
-    fn into(self) -> pnet::datalink::NetworkInterface {
-        self.interface
+impl From<NetworkInterface> for pnet::datalink::NetworkInterface {
+    fn from(value: NetworkInterface) -> Self {
+        value.interface
     }
 }
 
@@ -70,20 +93,32 @@ pub fn get_interfaces() -> Vec<NetworkInterface> {
 }
 
 pub fn get_interface_by_name(name: &str) -> Result<NetworkInterface, InterfaceError> {
-    match get_interfaces().into_iter().
-    filter(|iface: &NetworkInterface| iface.name().eq(name)).next() {
+    match get_interfaces().into_iter().find(|iface: &NetworkInterface| iface.name().eq(name)) {
         Some(interface) => Ok(interface),
         None => Err(InterfaceError::InterfaceNotFound)
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug)]
 pub enum InterfaceError {
     InterfaceNotFound,
     MissingIP,
     MissingMAC,
     NameAmbiguity,
-    ChannelError
+    ChannelError(io::Error),
+    PermissionDenied(io::Error),
+    UnsupportedChannel,
+    TransmissionError(io::Error)
+}
+
+impl InterfaceError {
+    /// Classifies an error returned when opening a datalink channel.
+    pub fn from_channel_error(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(err),
+            _ => Self::ChannelError(err)
+        }
+    }
 }
 
 impl fmt::Display for InterfaceError {
@@ -93,9 +128,19 @@ impl fmt::Display for InterfaceError {
             Self::NameAmbiguity => write!(f, "There is more than one interface with this name."),
             Self::MissingIP => write!(f, "This interface has no valid IPv4 address assigned."),
             Self::MissingMAC => write!(f, "This interface has no valid MAC address assigned."),
-            Self::ChannelError => write!(f, "Failed to open transmission channels on the interface.")
+            Self::ChannelError(err) => write!(f, "Failed to open transmission channels on the interface: {}", err),
+            Self::PermissionDenied(_) => write!(f, "Permission denied when opening a raw socket. Run as root or grant the binary the CAP_NET_RAW capability (e.g. `setcap cap_net_raw+ep arprender`)."),
+            Self::UnsupportedChannel => write!(f, "The interface does not provide an Ethernet channel."),
+            Self::TransmissionError(err) => write!(f, "Failed to transmit or receive on the interface: {}", err)
         }
     }
 }
 
-impl std::error::Error for InterfaceError {}
\ No newline at end of file
+impl std::error::Error for InterfaceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self {
+            Self::ChannelError(err) | Self::PermissionDenied(err) | Self::TransmissionError(err) => Some(err),
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use pnet::util::MacAddr;
+use serde::{Deserialize, Serialize};
+
+/// A small set of common vendors which is always available, even without a database file.
+const BUILTIN_VENDORS: &[([u8; 3], &str)] = &[
+    ([0x00, 0x00, 0x0C], "Cisco Systems"),
+    ([0x00, 0x00, 0x5E], "IANA"),
+    ([0x00, 0x03, 0x93], "Apple"),
+    ([0x00, 0x05, 0x69], "VMware"),
+    ([0x00, 0x05, 0x85], "Juniper Networks"),
+    ([0x00, 0x09, 0x0F], "Fortinet"),
+    ([0x00, 0x09, 0xBF], "Nintendo"),
+    ([0x00, 0x0A, 0x95], "Apple"),
+    ([0x00, 0x0C, 0x29], "VMware"),
+    ([0x00, 0x0C, 0x42], "MikroTik"),
+    ([0x00, 0x0D, 0x3A], "Microsoft"),
+    ([0x00, 0x0E, 0x58], "Sonos"),
+    ([0x00, 0x10, 0x18], "Broadcom"),
+    ([0x00, 0x11, 0x32], "Synology"),
+    ([0x00, 0x14, 0x22], "Dell"),
+    ([0x00, 0x15, 0x5D], "Microsoft (Hyper-V)"),
+    ([0x00, 0x16, 0x3E], "Xensource"),
+    ([0x00, 0x17, 0x88], "Philips Lighting"),
+    ([0x00, 0x18, 0x0A], "Cisco Meraki"),
+    ([0x00, 0x1A, 0x11], "Google"),
+    ([0x00, 0x1B, 0x17], "Palo Alto Networks"),
+    ([0x00, 0x1B, 0x21], "Intel"),
+    ([0x00, 0x1B, 0x63], "Apple"),
+    ([0x00, 0x1C, 0x14], "VMware"),
+    ([0x00, 0x1C, 0x42], "Parallels"),
+    ([0x00, 0x1C, 0x73], "Arista Networks"),
+    ([0x00, 0x25, 0x00], "Apple"),
+    ([0x00, 0x25, 0x90], "Super Micro Computer"),
+    ([0x00, 0x30, 0x48], "Super Micro Computer"),
+    ([0x00, 0x50, 0x56], "VMware"),
+    ([0x00, 0x50, 0xF2], "Microsoft"),
+    ([0x00, 0xE0, 0x4C], "Realtek"),
+    ([0x00, 0xE0, 0xFC], "Huawei"),
+    ([0x08, 0x00, 0x27], "Oracle VirtualBox"),
+    ([0x0C, 0xC4, 0x7A], "Super Micro Computer"),
+    ([0x18, 0xB4, 0x30], "Nest Labs"),
+    ([0x24, 0x0A, 0xC4], "Espressif"),
+    ([0x24, 0xA4, 0x3C], "Ubiquiti"),
+    ([0x28, 0xCD, 0xC1], "Raspberry Pi"),
+    ([0x30, 0xAE, 0xA4], "Espressif"),
+    ([0x3C, 0x5A, 0xB4], "Google"),
+    ([0x44, 0xD9, 0xE7], "Ubiquiti"),
+    ([0x4C, 0x5E, 0x0C], "MikroTik"),
+    ([0x52, 0x54, 0x00], "QEMU/KVM"),
+    ([0x80, 0x2A, 0xA8], "Ubiquiti"),
+    ([0xB8, 0x27, 0xEB], "Raspberry Pi"),
+    ([0xD8, 0x3A, 0xDD], "Raspberry Pi"),
+    ([0xDC, 0xA6, 0x32], "Raspberry Pi"),
+    ([0xE4, 0x5F, 0x01], "Raspberry Pi"),
+    ([0xFC, 0xEC, 0xDA], "Ubiquiti"),
+];
+
+/// A database mapping Organizationally Unique Identifiers (the first three bytes of a MAC address) to vendors.
+#[derive(Debug, Clone, Default)]
+pub struct OuiDatabase {
+    vendors: HashMap<[u8; 3], String>,
+}
+
+impl OuiDatabase {
+    /// The database compiled into the binary, which covers a selection of common vendors.
+    pub fn builtin() -> Self {
+        Self {
+            vendors: BUILTIN_VENDORS
+                .iter()
+                .map(|(oui, vendor)| (*oui, vendor.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Loads the IEEE `oui.txt` registry or a Wireshark `manuf` file. The format is detected line by line, and
+    /// `manuf` entries for blocks smaller than an OUI are skipped.
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut database = Self::default();
+
+        for line in contents.lines() {
+            if let Some((oui, vendor)) = parse_line(line) {
+                database.vendors.insert(oui, vendor);
+            }
+        }
+
+        Ok(database)
+    }
+
+    /// Adds every entry of `other`, replacing any existing entry for the same OUI.
+    pub fn extend(&mut self, other: OuiDatabase) {
+        self.vendors.extend(other.vendors);
+    }
+
+    /// Looks up the vendor of a MAC address. Locally-administered addresses are not assigned by the IEEE, so they
+    /// only match an entry which was registered for them explicitly.
+    pub fn lookup(&self, mac: MacAddr) -> Option<&str> {
+        self.vendors
+            .get(&[mac.0, mac.1, mac.2])
+            .map(|vendor| vendor.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.vendors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vendors.is_empty()
+    }
+}
+
+/// Parses an `oui.txt` line such as `00-00-0C   (hex)  Cisco Systems, Inc`, or a tab-separated `manuf` line such as
+/// `00:00:0C  Cisco  Cisco Systems, Inc`.
+fn parse_line(line: &str) -> Option<([u8; 3], String)> {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (prefix, vendor) = if let Some((prefix, vendor)) = line.split_once("(hex)") {
+        (prefix.trim(), vendor.trim())
+    } else {
+        let mut fields = line.split('\t').map(str::trim).filter(|field| !field.is_empty());
+        let prefix = fields.next()?;
+        let short_name = fields.next()?;
+
+        // The long name is optional
+        (prefix, fields.next().unwrap_or(short_name))
+    };
+
+    let prefix = match prefix.split_once('/') {
+        Some((prefix, "24")) => prefix,
+        Some(_) => return None,
+        None => prefix,
+    };
+
+    let bytes: Vec<u8> = prefix
+        .split(['-', ':', '.'])
+        .map(|byte| u8::from_str_radix(byte, 16))
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    if bytes.len() < 3 || vendor.is_empty() {
+        return None;
+    }
+
+    Some(([bytes[0], bytes[1], bytes[2]], vendor.to_string()))
+}
+
+/// A notable property of a MAC address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MacFlag {
+    /// The address was not assigned by the IEEE, as is the case for randomized addresses.
+    LocallyAdministered,
+    /// The address designates a group of stations.
+    Multicast,
+    /// A VRRP virtual router address (00:00:5E:00:01:XX).
+    Vrrp,
+    /// An HSRP virtual router address (00:00:0C:07:AC:XX or 00:00:0C:9F:FX:XX).
+    Hsrp,
+}
+
+impl MacFlag {
+    /// Determines the flags which apply to a MAC address.
+    pub fn for_mac(mac: MacAddr) -> Vec<MacFlag> {
+        let mut flags = Vec::new();
+
+        if mac.0 & 0x02 != 0 {
+            flags.push(Self::LocallyAdministered);
+        }
+
+        if mac.0 & 0x01 != 0 {
+            flags.push(Self::Multicast);
+        }
+
+        match (mac.0, mac.1, mac.2, mac.3, mac.4) {
+            (0x00, 0x00, 0x5E, 0x00, 0x01) => flags.push(Self::Vrrp),
+            (0x00, 0x00, 0x0C, 0x07, 0xAC) => flags.push(Self::Hsrp),
+            (0x00, 0x00, 0x0C, 0x9F, hsrp) if hsrp & 0xF0 == 0xF0 => flags.push(Self::Hsrp),
+            _ => {}
+        }
+
+        flags
+    }
+
+    /// The flag's name in serialized output.
+    pub fn name(&self) -> &'static str {
+        match &self {
+            Self::LocallyAdministered => "locally-administered",
+            Self::Multicast => "multicast",
+            Self::Vrrp => "vrrp",
+            Self::Hsrp => "hsrp",
+        }
+    }
+}
+
+impl fmt::Display for MacFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::LocallyAdministered => write!(f, "locally administered"),
+            Self::Multicast => write!(f, "multicast"),
+            Self::Vrrp => write!(f, "VRRP virtual MAC"),
+            Self::Hsrp => write!(f, "HSRP virtual MAC"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_oui_txt_line() {
+        assert_eq!(
+            parse_line("00-00-0C   (hex)\t\tCisco Systems, Inc"),
+            Some(([0x00, 0x00, 0x0C], "Cisco Systems, Inc".to_string()))
+        );
+
+        // The base-16 line which follows each entry carries the same information
+        assert_eq!(parse_line("00000C     (base 16)\t\tCisco Systems, Inc"), None);
+    }
+
+    #[test]
+    fn parses_manuf_lines() {
+        assert_eq!(
+            parse_line("00:00:0C\tCisco\tCisco Systems, Inc"),
+            Some(([0x00, 0x00, 0x0C], "Cisco Systems, Inc".to_string()))
+        );
+        assert_eq!(
+            parse_line("00:50:56\tVMware"),
+            Some(([0x00, 0x50, 0x56], "VMware".to_string()))
+        );
+        assert_eq!(
+            parse_line("08:00:27:00:00:00/24\tPcsCompu\tPCS Systemtechnik GmbH"),
+            Some(([0x08, 0x00, 0x27], "PCS Systemtechnik GmbH".to_string()))
+        );
+    }
+
+    #[test]
+    fn skips_blocks_smaller_than_an_oui() {
+        assert_eq!(parse_line("00:1B:C5:00:00:00/36\tConverge\tConverging Systems Inc."), None);
+        assert_eq!(parse_line("70:B3:D5:00:00:00/28\tIeeeRegi\tIEEE Registration Authority"), None);
+    }
+
+    #[test]
+    fn skips_comments_and_malformed_lines() {
+        assert_eq!(parse_line("# Wireshark manuf file"), None);
+        assert_eq!(parse_line(""), None);
+        assert_eq!(parse_line("00:00\tShort"), None);
+        assert_eq!(parse_line("ZZ:00:0C\tCisco"), None);
+        assert_eq!(parse_line("00-00-0C   (hex)"), None);
+    }
+}
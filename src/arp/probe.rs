@@ -0,0 +1,111 @@
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::Duration;
+
+use pnet::util::MacAddr;
+use rand::Rng;
+
+use super::nic::InterfaceError;
+use super::ArpSession;
+
+// Protocol constants from RFC 5227, section 1.1.
+/// The upper bound of the random delay before the first probe.
+pub const PROBE_WAIT: Duration = Duration::from_secs(1);
+/// The number of probes to send.
+pub const PROBE_NUM: u32 = 3;
+/// The minimum delay between two probes.
+pub const PROBE_MIN: Duration = Duration::from_secs(1);
+/// The maximum delay between two probes.
+pub const PROBE_MAX: Duration = Duration::from_secs(2);
+/// The delay between the last probe and the first announcement.
+pub const ANNOUNCE_WAIT: Duration = Duration::from_secs(2);
+/// The number of announcements to send once the address has been claimed.
+pub const ANNOUNCE_NUM: u32 = 2;
+/// The delay between two announcements.
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The result of probing an IP address for conflicts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    /// No other host answered or probed the address.
+    Available,
+    /// Another host sent an ARP packet with the address as its sender IP, so it is already in use.
+    InUse(MacAddr),
+    /// Another host is probing for the same address at the same time.
+    Contested(MacAddr),
+}
+
+impl ArpSession {
+    /// Checks whether the given IP address is in use by another host, as described in RFC 5227.
+    ///
+    /// ARP probes (requests with a sender IP of 0.0.0.0) are sent at randomized intervals, whilst listening for any
+    /// host which claims the address or is probing for it as well.
+    ///
+    /// Note: This function does not claim the address. To announce it once it is known to be free, use announce_claim.
+    pub fn probe(&mut self, proto_addr: Ipv4Addr) -> Result<ProbeOutcome, InterfaceError> {
+        let Some(hw_addr) = self.interface().mac() else {
+            return Err(InterfaceError::MissingMAC);
+        };
+
+        let mut rng = rand::thread_rng();
+        let mut wait = rng.gen_range(Duration::ZERO..PROBE_WAIT);
+
+        for _ in 0..PROBE_NUM {
+            if let Some(conflict) = self.watch_for_conflict(proto_addr, hw_addr, wait)? {
+                return Ok(conflict);
+            }
+
+            self.send_request(proto_addr, Some(hw_addr), Some(Ipv4Addr::UNSPECIFIED), None)?;
+
+            wait = rng.gen_range(PROBE_MIN..=PROBE_MAX);
+        }
+
+        match self.watch_for_conflict(proto_addr, hw_addr, ANNOUNCE_WAIT)? {
+            Some(conflict) => Ok(conflict),
+            None => Ok(ProbeOutcome::Available),
+        }
+    }
+
+    /// Claims the given IP address for the interface by sending ANNOUNCE_NUM gratuitous ARP requests.
+    pub fn announce_claim(&mut self, proto_addr: Ipv4Addr) -> Result<(), InterfaceError> {
+        for i in 0..ANNOUNCE_NUM {
+            if i > 0 {
+                thread::sleep(ANNOUNCE_INTERVAL);
+            }
+
+            self.send_gratuitous_request(proto_addr, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Listens for another host using or probing for the address.
+    fn watch_for_conflict(
+        &mut self,
+        proto_addr: Ipv4Addr,
+        hw_addr: MacAddr,
+        timeout: Duration,
+    ) -> Result<Option<ProbeOutcome>, InterfaceError> {
+        let mut conflict = None;
+
+        self.receive(timeout, |frame| {
+            // Our own probes are not a conflict
+            if frame.sender_hw_addr == hw_addr {
+                return false;
+            }
+
+            if frame.sender_proto_addr == proto_addr {
+                conflict = Some(ProbeOutcome::InUse(frame.sender_hw_addr));
+            } else if frame.is_request()
+                && frame.sender_proto_addr == Ipv4Addr::UNSPECIFIED
+                && frame.target_proto_addr == proto_addr
+            {
+                conflict = Some(ProbeOutcome::Contested(frame.sender_hw_addr));
+            }
+
+            conflict.is_some()
+        })?;
+
+        Ok(conflict)
+    }
+}
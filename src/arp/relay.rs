@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use pnet::datalink::{DataLinkReceiver, DataLinkSender};
+use pnet::ipnetwork::Ipv4Network;
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::tcp::TcpPacket;
+use pnet::packet::udp::UdpPacket;
+use pnet::packet::Packet;
+use pnet::util::MacAddr;
+
+use super::nic::{InterfaceError, NetworkInterface};
+use super::session::{next_frame, open_channel, transmit};
+use super::{CancelHandle, Peer};
+
+/// A decision made by the inspection hook about an intercepted frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Pass the frame on to its real destination.
+    Forward,
+    /// Silently discard the frame.
+    Drop,
+}
+
+/// The IPv4 traffic between two endpoints. Ports are only known for TCP and UDP, and only in the first fragment of a
+/// fragmented packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Flow {
+    pub protocol: IpNextHeaderProtocol,
+    pub source: Ipv4Addr,
+    pub source_port: Option<u16>,
+    pub destination: Ipv4Addr,
+    pub destination_port: Option<u16>,
+}
+
+impl fmt::Display for Flow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let endpoint = |ip: Ipv4Addr, port: Option<u16>| match port {
+            Some(port) => format!("{}:{}", ip, port),
+            None => ip.to_string(),
+        };
+
+        write!(
+            f,
+            "{} {} -> {}",
+            self.protocol,
+            endpoint(self.source, self.source_port),
+            endpoint(self.destination, self.destination_port)
+        )
+    }
+}
+
+/// The traffic relayed for a single flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowStats {
+    /// The number of frames relayed.
+    pub frames: u64,
+    /// The total size of the relayed frames, including their Ethernet headers.
+    pub bytes: u64,
+    /// The number of frames discarded by the inspection hook.
+    pub dropped: u64,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+}
+
+type Inspector = Box<dyn FnMut(&Flow, &mut [u8]) -> Verdict + Send>;
+
+/// A userspace relay which passes intercepted traffic on to the poisoned peers, as an alternative to kernel IP
+/// forwarding.
+///
+/// Once a peer's traffic has been redirected to us, the relay picks up IPv4 frames which are addressed to the
+/// interface's MAC address but not to its IP address. Frames for a peer's IP address, or for any address outside of
+/// the network if a gateway is set, get their Ethernet destination rewritten to the real MAC address of the peer and
+/// their Ethernet source to ours, and are then retransmitted.
+///
+/// Note: Kernel IP forwarding should be disabled whilst the relay is running, otherwise every frame is delivered twice.
+pub struct Relay {
+    interface: NetworkInterface,
+    tx: Box<dyn DataLinkSender>,
+    rx: Box<dyn DataLinkReceiver>,
+    cancelled: Arc<AtomicBool>,
+    routes: Routes,
+    inspector: Option<Inspector>,
+    flows: HashMap<Flow, FlowStats>,
+}
+
+impl Relay {
+    /// Opens a datalink channel for relaying on the given interface.
+    pub fn new(interface: &NetworkInterface) -> Result<Self, InterfaceError> {
+        let (tx, rx) = open_channel(interface)?;
+
+        Ok(Self {
+            interface: interface.clone(),
+            tx,
+            rx,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            routes: Routes {
+                peers: HashMap::new(),
+                gateway: None,
+                network: interface.network(),
+            },
+            inspector: None,
+            flows: HashMap::new(),
+        })
+    }
+
+    /// Returns a handle which can be used to stop the relay from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
+    }
+
+    /// Relays the traffic for a peer's IP address to its real MAC address.
+    pub fn add_peer(&mut self, peer: Peer) {
+        self.routes.peers.insert(peer.ip, peer.mac);
+    }
+
+    /// Relays the traffic for addresses outside of the network to the gateway, which is added as a peer too.
+    pub fn set_gateway(&mut self, gateway: Peer) {
+        self.add_peer(gateway);
+        self.routes.gateway = Some(gateway);
+    }
+
+    /// Installs a hook which is shown every frame before it is relayed. The hook may modify the frame in place and
+    /// decides whether it is forwarded or dropped.
+    pub fn set_inspector<F>(&mut self, inspector: F)
+    where
+        F: FnMut(&Flow, &mut [u8]) -> Verdict + Send + 'static,
+    {
+        self.inspector = Some(Box::new(inspector));
+    }
+
+    /// Relays frames until `duration` has passed or, if it is `None`, until cancelled.
+    pub fn run(&mut self, duration: Option<Duration>) -> Result<(), InterfaceError> {
+        let Some(local_mac) = self.interface.mac() else {
+            return Err(InterfaceError::MissingMAC);
+        };
+        let local_ip = self.interface.ipv4_address();
+
+        let deadline = duration.map(|duration| Instant::now() + duration);
+
+        while !self.cancelled.load(Ordering::SeqCst)
+            && deadline.is_none_or(|deadline| Instant::now() < deadline)
+        {
+            let Some(buf) = next_frame(&mut self.rx)? else {
+                continue;
+            };
+
+            let Some((flow, dest_mac)) = self.routes.route(buf, local_mac, local_ip) else {
+                continue;
+            };
+            let mut frame = buf.to_vec();
+
+            {
+                let mut ethernet = MutableEthernetPacket::new(&mut frame).unwrap();
+                ethernet.set_destination(dest_mac);
+                ethernet.set_source(local_mac);
+            }
+
+            let verdict = match &mut self.inspector {
+                Some(inspector) => inspector(&flow, &mut frame),
+                None => Verdict::Forward,
+            };
+
+            let now = SystemTime::now();
+            let stats = self.flows.entry(flow).or_insert(FlowStats {
+                frames: 0,
+                bytes: 0,
+                dropped: 0,
+                first_seen: now,
+                last_seen: now,
+            });
+            stats.last_seen = now;
+
+            match verdict {
+                Verdict::Forward => {
+                    transmit(&mut self.tx, &frame)?;
+                    stats.frames += 1;
+                    stats.bytes += frame.len() as u64;
+                }
+                Verdict::Drop => stats.dropped += 1,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The flows relayed so far, with the busiest first.
+    pub fn flows(&self) -> Vec<(Flow, FlowStats)> {
+        let mut flows: Vec<(Flow, FlowStats)> = self.flows.iter().map(|(flow, stats)| (*flow, *stats)).collect();
+        flows.sort_by(|(a, a_stats), (b, b_stats)| b_stats.bytes.cmp(&a_stats.bytes).then(a.cmp(b)));
+
+        flows
+    }
+}
+
+/// Where intercepted traffic is relayed to.
+struct Routes {
+    /// The real MAC addresses of the poisoned peers.
+    peers: HashMap<Ipv4Addr, MacAddr>,
+    gateway: Option<Peer>,
+    network: Option<Ipv4Network>,
+}
+
+impl Routes {
+    /// Determines the flow of an intercepted frame and the real MAC address it should be relayed to, or `None` if the
+    /// frame is not meant to be relayed.
+    fn route(&self, buf: &[u8], local_mac: MacAddr, local_ip: Option<Ipv4Addr>) -> Option<(Flow, MacAddr)> {
+        let ethernet = EthernetPacket::new(buf)?;
+
+        // Our own retransmissions are addressed to the peers, so they never get relayed twice
+        if ethernet.get_destination() != local_mac || ethernet.get_ethertype() != EtherTypes::Ipv4 {
+            return None;
+        }
+
+        let ip = Ipv4Packet::new(ethernet.payload())?;
+        let destination = ip.get_destination();
+
+        if Some(destination) == local_ip {
+            return None;
+        }
+
+        let dest_mac = match self.peers.get(&destination) {
+            Some(mac) => *mac,
+            None => match (self.gateway, self.network) {
+                (Some(gateway), Some(network)) if !network.contains(destination) => gateway.mac,
+                _ => return None,
+            },
+        };
+
+        Some((flow_of(&ip), dest_mac))
+    }
+}
+
+fn flow_of(ip: &Ipv4Packet) -> Flow {
+    let protocol = ip.get_next_level_protocol();
+
+    // Only the first fragment carries the transport header
+    let ports = if ip.get_fragment_offset() != 0 {
+        None
+    } else if protocol == IpNextHeaderProtocols::Tcp {
+        TcpPacket::new(ip.payload()).map(|tcp| (tcp.get_source(), tcp.get_destination()))
+    } else if protocol == IpNextHeaderProtocols::Udp {
+        UdpPacket::new(ip.payload()).map(|udp| (udp.get_source(), udp.get_destination()))
+    } else {
+        None
+    };
+
+    Flow {
+        protocol,
+        source: ip.get_source(),
+        source_port: ports.map(|(source, _)| source),
+        destination: ip.get_destination(),
+        destination_port: ports.map(|(_, destination)| destination),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::ethernet::EtherType;
+    use pnet::packet::ipv4::MutableIpv4Packet;
+    use pnet::packet::udp::MutableUdpPacket;
+
+    use super::*;
+
+    const LOCAL_MAC: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 0x01);
+    const LOCAL_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+    const PEER: Peer = Peer {
+        ip: Ipv4Addr::new(10, 0, 0, 3),
+        mac: MacAddr(0x02, 0, 0, 0, 0, 0x03),
+    };
+    const GATEWAY: Peer = Peer {
+        ip: Ipv4Addr::new(10, 0, 0, 1),
+        mac: MacAddr(0x02, 0, 0, 0, 0, 0x04),
+    };
+    const REMOTE_IP: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 80);
+
+    /// Builds a UDP datagram from port 5353 to port 53, sent from 10.0.0.4.
+    fn frame(
+        destination_mac: MacAddr,
+        ethertype: EtherType,
+        destination: Ipv4Addr,
+        fragment_offset: u16,
+    ) -> Vec<u8> {
+        let mut buf = vec![0u8; 14 + 20 + 8];
+
+        let mut ethernet = MutableEthernetPacket::new(&mut buf).unwrap();
+        ethernet.set_destination(destination_mac);
+        ethernet.set_source(MacAddr(0x02, 0, 0, 0, 0, 0x05));
+        ethernet.set_ethertype(ethertype);
+
+        let mut ip = MutableIpv4Packet::new(&mut buf[14..]).unwrap();
+        ip.set_version(4);
+        ip.set_header_length(5);
+        ip.set_total_length(28);
+        ip.set_fragment_offset(fragment_offset);
+        ip.set_next_level_protocol(IpNextHeaderProtocols::Udp);
+        ip.set_source(Ipv4Addr::new(10, 0, 0, 4));
+        ip.set_destination(destination);
+
+        let mut udp = MutableUdpPacket::new(&mut buf[34..]).unwrap();
+        udp.set_source(5353);
+        udp.set_destination(53);
+        udp.set_length(8);
+
+        buf
+    }
+
+    fn routes(gateway: Option<Peer>) -> Routes {
+        Routes {
+            peers: HashMap::from([(PEER.ip, PEER.mac)]),
+            gateway,
+            network: Some("10.0.0.0/24".parse().unwrap()),
+        }
+    }
+
+    fn route(routes: &Routes, buf: &[u8]) -> Option<(Flow, MacAddr)> {
+        routes.route(buf, LOCAL_MAC, Some(LOCAL_IP))
+    }
+
+    #[test]
+    fn routes_peer_to_its_real_mac() {
+        let (flow, mac) = route(
+            &routes(None),
+            &frame(LOCAL_MAC, EtherTypes::Ipv4, PEER.ip, 0),
+        )
+        .unwrap();
+
+        assert_eq!(mac, PEER.mac);
+        assert_eq!(
+            flow,
+            Flow {
+                protocol: IpNextHeaderProtocols::Udp,
+                source: Ipv4Addr::new(10, 0, 0, 4),
+                source_port: Some(5353),
+                destination: PEER.ip,
+                destination_port: Some(53),
+            }
+        );
+    }
+
+    #[test]
+    fn skips_traffic_for_local_ip() {
+        assert_eq!(
+            route(
+                &routes(Some(GATEWAY)),
+                &frame(LOCAL_MAC, EtherTypes::Ipv4, LOCAL_IP, 0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn ignores_frames_not_addressed_to_local_mac() {
+        assert_eq!(
+            route(
+                &routes(None),
+                &frame(PEER.mac, EtherTypes::Ipv4, PEER.ip, 0)
+            ),
+            None
+        );
+        assert_eq!(
+            route(
+                &routes(None),
+                &frame(MacAddr::broadcast(), EtherTypes::Ipv4, PEER.ip, 0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn ignores_non_ipv4_frames() {
+        assert_eq!(
+            route(
+                &routes(None),
+                &frame(LOCAL_MAC, EtherTypes::Ipv6, PEER.ip, 0)
+            ),
+            None
+        );
+        assert_eq!(
+            route(
+                &routes(None),
+                &frame(LOCAL_MAC, EtherTypes::Arp, PEER.ip, 0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn routes_off_network_traffic_to_gateway_only_if_set() {
+        let buf = frame(LOCAL_MAC, EtherTypes::Ipv4, REMOTE_IP, 0);
+
+        assert_eq!(
+            route(&routes(Some(GATEWAY)), &buf).map(|(_, mac)| mac),
+            Some(GATEWAY.mac)
+        );
+        assert_eq!(route(&routes(None), &buf), None);
+
+        // Hosts of the network which are not peers are reached directly, so they are not relayed
+        let on_link = frame(LOCAL_MAC, EtherTypes::Ipv4, Ipv4Addr::new(10, 0, 0, 9), 0);
+        assert_eq!(route(&routes(Some(GATEWAY)), &on_link), None);
+    }
+
+    #[test]
+    fn later_fragments_have_no_ports() {
+        let (flow, _) = route(
+            &routes(None),
+            &frame(LOCAL_MAC, EtherTypes::Ipv4, PEER.ip, 185),
+        )
+        .unwrap();
+
+        assert_eq!(flow.protocol, IpNextHeaderProtocols::Udp);
+        assert_eq!(flow.source_port, None);
+        assert_eq!(flow.destination_port, None);
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::Ipv4Addr;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pnet::datalink::{self, Channel, DataLinkReceiver, DataLinkSender};
+use pnet::packet::arp::ArpOperations;
+use pnet::util::MacAddr;
+
+use super::nic::{InterfaceError, NetworkInterface};
+use super::host::record_sender;
+use super::{ArpFrame, ArpFrameBuilder, Host, TargetSet};
+
+/// How long a single read on the datalink channel may block before the receive loops get a chance to check their deadline
+/// and whether they have been cancelled.
+const RECV_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An ARP session bound to a single network interface.
+///
+/// The session opens one datalink channel when it is created and reuses it for every frame it sends or receives,
+/// so long-running scans and attacks do not have to open a new raw socket for each packet.
+///
+/// Receive loops never block for longer than a short poll interval, so every scan and resolution returns once its
+/// timeout expires or the session is cancelled, without leaving any threads behind.
+pub struct ArpSession {
+    interface: NetworkInterface,
+    tx: Box<dyn DataLinkSender>,
+    rx: Box<dyn DataLinkReceiver>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// A handle which stops the receive loops of an `ArpSession` from another thread.
+///
+/// A handle which is not tied to any session can be created as well, for example to stop a `Schedule`.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(pub(super) Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests that the session stop. Any scan, resolution or receive loop in progress returns with the results
+    /// gathered so far, and subsequent ones return immediately.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Settings which control the pacing of an ARP scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// How long to keep waiting for replies after the last request has been sent.
+    pub timeout: Duration,
+    /// The maximum number of requests to send per second. `None` sends them as fast as possible.
+    pub rate: Option<NonZeroU32>,
+    /// How many more times to send a request to targets which have not answered.
+    pub retries: u32,
+    /// The delay between the end of one round of requests and the start of the next.
+    pub retry_interval: Duration,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            rate: None,
+            retries: 0,
+            retry_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The progress of a scan, shared between its sending and receiving halves.
+#[derive(Default)]
+struct ScanState {
+    hosts: HashMap<Ipv4Addr, Host>,
+    /// When the request to each address was last sent.
+    sent_at: HashMap<Ipv4Addr, Instant>,
+    /// When the last request of the scan was sent.
+    sent_all: Option<Instant>,
+    /// Whether sending failed, in which case the listener stops without waiting for the timeout.
+    aborted: bool,
+}
+
+impl ArpSession {
+    /// Opens a datalink channel on the given interface.
+    pub fn new(interface: &NetworkInterface) -> Result<Self, InterfaceError> {
+        let (tx, rx) = open_channel(interface)?;
+
+        Ok(Self {
+            interface: interface.clone(),
+            tx,
+            rx,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Returns a handle which can be used to cancel this session from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// The interface this session is bound to.
+    pub fn interface(&self) -> &NetworkInterface {
+        &self.interface
+    }
+
+    /// Transmits a raw frame, such as one produced by an `ArpFrameBuilder`, on the session's interface.
+    pub fn send_frame(&mut self, frame: &[u8]) -> Result<(), InterfaceError> {
+        transmit(&mut self.tx, frame)
+    }
+
+    /// Sends an ARP request.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_proto_addr` - The destination IP address of the ARP request.
+    /// * `sender_hw_addr` - An optional MAC address to use as the source MAC for the ARP request. If `None` is specified, then the interface's MAC address is used.
+    /// * `sender_proto_addr` - An optional IP address to use as the source IP for the ARP request. If `None` is specified, then the interface's IP address is used.
+    /// * `eth_dest` - An optional Ethernet destination for the frame. If `None` is specified, then the request is broadcast.
+    ///
+    /// Note: This function does not await a response. To resolve an IP address, use resolve.
+    pub fn send_request(
+        &mut self,
+        target_proto_addr: Ipv4Addr,
+        sender_hw_addr: Option<MacAddr>,
+        sender_proto_addr: Option<Ipv4Addr>,
+        eth_dest: Option<MacAddr>,
+    ) -> Result<(), InterfaceError> {
+        let frame = request_frame(
+            &self.interface,
+            target_proto_addr,
+            sender_hw_addr,
+            sender_proto_addr,
+            eth_dest,
+        )?;
+
+        self.send_frame(&frame)
+    }
+
+    /// Sends an ARP response packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_hw_addr` - The MAC address of the host the reply is addressed to.
+    /// * `target_proto_addr` - The IP address of the host the reply is addressed to.
+    /// * `sender_hw_addr` - An optional MAC address to announce. If `None` is specified, then the interface's MAC address is used.
+    /// * `sender_proto_addr` - An optional IP address to announce. If `None` is specified, then the interface's IP address is used.
+    /// * `eth_dest` - An optional Ethernet destination for the frame. If `None` is specified, then the reply is unicast to `target_hw_addr`.
+    pub fn send_reply(
+        &mut self,
+        target_hw_addr: MacAddr,
+        target_proto_addr: Ipv4Addr,
+        sender_hw_addr: Option<MacAddr>,
+        sender_proto_addr: Option<Ipv4Addr>,
+        eth_dest: Option<MacAddr>,
+    ) -> Result<(), InterfaceError> {
+        let frame = reply_frame(
+            &self.interface,
+            target_hw_addr,
+            target_proto_addr,
+            sender_hw_addr,
+            sender_proto_addr,
+            eth_dest,
+        )?;
+
+        self.send_frame(&frame)
+    }
+
+    /// Sends a gratuitous ARP request, in which the sender and target IP addresses are both `proto_addr`.
+    ///
+    /// # Arguments
+    ///
+    /// * `proto_addr` - The IP address to announce.
+    /// * `hw_addr` - An optional MAC address to announce for the IP. If `None` is specified, then the interface's MAC address is used.
+    pub fn send_gratuitous_request(
+        &mut self,
+        proto_addr: Ipv4Addr,
+        hw_addr: Option<MacAddr>,
+    ) -> Result<(), InterfaceError> {
+        self.send_request(proto_addr, hw_addr, Some(proto_addr), None)
+    }
+
+    /// Sends a gratuitous ARP reply, in which the sender and target addresses both describe the announced host.
+    /// The reply is broadcast, so that every station on the segment updates its cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `proto_addr` - The IP address to announce.
+    /// * `hw_addr` - An optional MAC address to announce for the IP. If `None` is specified, then the interface's MAC address is used.
+    pub fn send_gratuitous_reply(
+        &mut self,
+        proto_addr: Ipv4Addr,
+        hw_addr: Option<MacAddr>,
+    ) -> Result<(), InterfaceError> {
+        let Some(hw_addr) = hw_addr.or(self.interface.mac()) else {
+            return Err(InterfaceError::MissingMAC);
+        };
+
+        self.send_reply(
+            hw_addr,
+            proto_addr,
+            Some(hw_addr),
+            Some(proto_addr),
+            Some(MacAddr::broadcast()),
+        )
+    }
+
+    /// Attempts to find the MAC associated with the given IP address.
+    pub fn resolve(
+        &mut self,
+        dest_ip: Ipv4Addr,
+        timeout: Duration,
+    ) -> Result<Option<MacAddr>, InterfaceError> {
+        self.send_request(dest_ip, None, None, None)?;
+        self.await_reply(dest_ip, timeout)
+    }
+
+    /// Checks whether the host with the given IP address still answers at a known MAC address.
+    ///
+    /// The request is unicast to `dest_hw_addr`, as is done when validating an ARP cache entry (RFC 1122, 2.3.2.1),
+    /// so other stations on the segment never see it.
+    pub fn verify(
+        &mut self,
+        dest_ip: Ipv4Addr,
+        dest_hw_addr: MacAddr,
+        timeout: Duration,
+    ) -> Result<bool, InterfaceError> {
+        self.send_request(dest_ip, None, None, Some(dest_hw_addr))?;
+
+        Ok(self.await_reply(dest_ip, timeout)? == Some(dest_hw_addr))
+    }
+
+    /// Sends an ARP request to every target address and collects the replies. To scan the interface's whole
+    /// network, use `TargetSet::for_interface`.
+    ///
+    /// Requests are paced and retried according to the options, with retries only going to targets which have not
+    /// answered yet. The scan ends `options.timeout` after the last request has been sent, or as soon as every target
+    /// has answered.
+    ///
+    /// The hosts are returned ordered by IP address.
+    pub fn scan(
+        &mut self,
+        targets: &TargetSet,
+        options: &ScanOptions,
+    ) -> Result<Vec<Host>, InterfaceError> {
+        let Some(interface_mac) = self.interface.mac() else {
+            return Err(InterfaceError::MissingMAC);
+        };
+
+        let target_count = targets.len();
+        let send_interval = options
+            .rate
+            .map(|rate| Duration::from_secs_f64(1.0 / f64::from(rate.get())));
+
+        let state = Mutex::new(ScanState::default());
+
+        // The sending and receiving halves of the channel are borrowed separately, so that replies can be collected
+        // while the requests are still going out.
+        let Self {
+            interface,
+            tx,
+            rx,
+            cancelled,
+        } = self;
+        let interface = &*interface;
+        let cancelled = &*cancelled;
+        let state = &state;
+
+        thread::scope(|scope| {
+            let listener = scope.spawn(move || {
+                let keep_receiving = || {
+                    let state = state.lock().unwrap();
+
+                    !cancelled.load(Ordering::SeqCst)
+                        && !state.aborted
+                        && (state.hosts.len() as u64) < target_count
+                        && state
+                            .sent_all
+                            .is_none_or(|sent_all| sent_all.elapsed() < options.timeout)
+                };
+
+                receive_frames(rx, Some(interface_mac), keep_receiving, |frame| {
+                    if !frame.is_reply()
+                        || frame.target_hw_addr != interface_mac
+                        || !targets.contains(frame.sender_proto_addr)
+                    {
+                        return false;
+                    }
+
+                    let state = &mut *state.lock().unwrap();
+                    let latency = state
+                        .sent_at
+                        .get(&frame.sender_proto_addr)
+                        .map(|sent| sent.elapsed());
+
+                    if let Some(host) = record_sender(&mut state.hosts, frame, interface.name()) {
+                        host.latency = latency;
+                    }
+
+                    false
+                })
+            });
+
+            // Stops sending once the scan is cancelled, every target has answered or the listener has given up
+            let should_stop = || {
+                cancelled.load(Ordering::SeqCst)
+                    || listener.is_finished()
+                    || state.lock().unwrap().hosts.len() as u64 >= target_count
+            };
+
+            let mut next_send = Instant::now();
+
+            let sent = (|| {
+                for round in 0..=options.retries {
+                    if round > 0 {
+                        let retry_at = Instant::now() + options.retry_interval;
+                        while Instant::now() < retry_at && !should_stop() {
+                            thread::sleep(RECV_POLL_INTERVAL.min(retry_at - Instant::now()));
+                        }
+                    }
+
+                    for ip in targets.iter() {
+                        if should_stop() {
+                            return Ok(());
+                        }
+
+                        if state.lock().unwrap().hosts.contains_key(&ip) {
+                            continue;
+                        }
+
+                        if let Some(send_interval) = send_interval {
+                            let now = Instant::now();
+                            if next_send > now {
+                                thread::sleep(next_send - now);
+                            }
+                            next_send = next_send.max(now) + send_interval;
+                        }
+
+                        let frame = request_frame(interface, ip, None, None, None)?;
+                        state.lock().unwrap().sent_at.insert(ip, Instant::now());
+                        transmit(tx, &frame)?;
+                    }
+                }
+
+                Ok(())
+            })();
+
+            // Let the listener wait out the timeout, or stop it right away if sending failed
+            {
+                let mut state = state.lock().unwrap();
+                state.sent_all = Some(Instant::now());
+                state.aborted = sent.is_err();
+            }
+
+            listener.join().unwrap()?;
+            sent
+        })?;
+
+        let mut hosts: Vec<Host> = state.lock().unwrap().hosts.drain().map(|(_, host)| host).collect();
+        hosts.sort_by_key(|host| host.ip);
+
+        Ok(hosts)
+    }
+
+    /// Receives ARP frames until the timeout expires or the session is cancelled, handing each one to `handler`.
+    /// Frames which fail validation, including the ones sent by this interface, are skipped.
+    ///
+    /// Returns `true` if the handler stopped the loop early by returning `true`.
+    pub fn receive<F>(&mut self, timeout: Duration, handler: F) -> Result<bool, InterfaceError>
+    where
+        F: FnMut(&ArpFrame) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        let cancelled = &self.cancelled;
+
+        receive_frames(
+            &mut self.rx,
+            self.interface.mac(),
+            || Instant::now() < deadline && !cancelled.load(Ordering::SeqCst),
+            handler,
+        )
+    }
+
+    /// Waits for an ARP reply from the given IP address which is addressed to this interface.
+    fn await_reply(
+        &mut self,
+        dest_ip: Ipv4Addr,
+        timeout: Duration,
+    ) -> Result<Option<MacAddr>, InterfaceError> {
+        let Some(interface_mac) = self.interface.mac() else {
+            return Err(InterfaceError::MissingMAC);
+        };
+
+        let mut reply = None;
+
+        self.receive(timeout, |frame| {
+            if frame.is_reply()
+                && frame.sender_proto_addr == dest_ip
+                && frame.target_hw_addr == interface_mac
+            {
+                reply = Some(frame.sender_hw_addr);
+            }
+
+            reply.is_some()
+        })?;
+
+        Ok(reply)
+    }
+}
+
+/// The sending and receiving halves of a datalink channel.
+pub(super) type ChannelPair = (Box<dyn DataLinkSender>, Box<dyn DataLinkReceiver>);
+
+/// Opens an Ethernet datalink channel on the interface whose reads time out after `RECV_POLL_INTERVAL`.
+pub(super) fn open_channel(interface: &NetworkInterface) -> Result<ChannelPair, InterfaceError> {
+    let config = datalink::Config {
+        read_timeout: Some(RECV_POLL_INTERVAL),
+        ..Default::default()
+    };
+
+    match datalink::channel(&interface.clone().into(), config) {
+        Ok(Channel::Ethernet(tx, rx)) => Ok((tx, rx)),
+        Ok(_) => Err(InterfaceError::UnsupportedChannel),
+        Err(e) => Err(InterfaceError::from_channel_error(e)),
+    }
+}
+
+/// Reads the next frame from the channel, returning `None` if the read timed out or was interrupted.
+pub(super) fn next_frame(rx: &mut Box<dyn DataLinkReceiver>) -> Result<Option<&[u8]>, InterfaceError> {
+    match rx.next() {
+        Ok(buf) => Ok(Some(buf)),
+        Err(e) => match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => {
+                Ok(None)
+            }
+            _ => Err(InterfaceError::TransmissionError(e)),
+        },
+    }
+}
+
+/// Receives ARP frames for as long as `keep_receiving` returns `true`, handing each one to `handler`. Frames which
+/// fail validation, including the ones sent from `local_hw_addr`, are skipped.
+///
+/// Returns `true` if the handler stopped the loop early by returning `true`.
+pub(super) fn receive_frames<C, F>(
+    rx: &mut Box<dyn DataLinkReceiver>,
+    local_hw_addr: Option<MacAddr>,
+    mut keep_receiving: C,
+    mut handler: F,
+) -> Result<bool, InterfaceError>
+where
+    C: FnMut() -> bool,
+    F: FnMut(&ArpFrame) -> bool,
+{
+    while keep_receiving() {
+        let Some(buf) = next_frame(rx)? else {
+            continue;
+        };
+
+        let Some(frame) = ArpFrame::parse(buf, local_hw_addr) else {
+            continue;
+        };
+
+        if handler(&frame) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Writes a single frame to the channel.
+pub(super) fn transmit(tx: &mut Box<dyn DataLinkSender>, frame: &[u8]) -> Result<(), InterfaceError> {
+    match tx.send_to(frame, None) {
+        Some(Err(e)) => Err(InterfaceError::TransmissionError(e)),
+        _ => Ok(()),
+    }
+}
+
+/// Builds an ARP request frame, addressed to the broadcast MAC unless `eth_dest` is given.
+fn request_frame(
+    interface: &NetworkInterface,
+    target_proto_addr: Ipv4Addr,
+    sender_hw_addr: Option<MacAddr>,
+    sender_proto_addr: Option<Ipv4Addr>,
+    eth_dest: Option<MacAddr>,
+) -> Result<Vec<u8>, InterfaceError> {
+    let (source_mac, source_ip) = sender_addrs(interface, sender_hw_addr, sender_proto_addr)?;
+
+    Ok(ArpFrameBuilder::new()
+        .ethernet_destination(eth_dest.unwrap_or(MacAddr::broadcast()))
+        .ethernet_source(source_mac)
+        .operation(ArpOperations::Request)
+        .sender_hw_addr(source_mac)
+        .sender_proto_addr(source_ip)
+        .target_hw_addr(MacAddr::zero())
+        .target_proto_addr(target_proto_addr)
+        .build())
+}
+
+/// Builds an ARP reply frame, addressed to the target MAC unless `eth_dest` is given.
+fn reply_frame(
+    interface: &NetworkInterface,
+    target_hw_addr: MacAddr,
+    target_proto_addr: Ipv4Addr,
+    sender_hw_addr: Option<MacAddr>,
+    sender_proto_addr: Option<Ipv4Addr>,
+    eth_dest: Option<MacAddr>,
+) -> Result<Vec<u8>, InterfaceError> {
+    let (source_mac, source_ip) = sender_addrs(interface, sender_hw_addr, sender_proto_addr)?;
+
+    Ok(ArpFrameBuilder::new()
+        .ethernet_destination(eth_dest.unwrap_or(target_hw_addr))
+        .ethernet_source(source_mac)
+        .operation(ArpOperations::Reply)
+        .sender_hw_addr(source_mac)
+        .sender_proto_addr(source_ip)
+        .target_hw_addr(target_hw_addr)
+        .target_proto_addr(target_proto_addr)
+        .build())
+}
+
+/// Falls back to the interface's addresses for any sender address which is not spoofed.
+fn sender_addrs(
+    interface: &NetworkInterface,
+    sender_hw_addr: Option<MacAddr>,
+    sender_proto_addr: Option<Ipv4Addr>,
+) -> Result<(MacAddr, Ipv4Addr), InterfaceError> {
+    let source_mac = match sender_hw_addr.or(interface.mac()) {
+        Some(mac) => mac,
+        None => return Err(InterfaceError::MissingMAC),
+    };
+
+    let source_ip = match sender_proto_addr.or(interface.ipv4_address()) {
+        Some(ip) => ip,
+        None => return Err(InterfaceError::MissingIP),
+    };
+
+    Ok((source_mac, source_ip))
+}
@@ -0,0 +1,376 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use pnet::ipnetwork::Ipv4Network;
+
+use super::nic::{InterfaceError, NetworkInterface};
+
+/// A set of IPv4 addresses to target, stored as sorted, non-overlapping ranges so that large networks stay cheap.
+///
+/// Targets are specified as single IPs (`10.0.0.1`), CIDRs (`10.0.0.0/24`), dash ranges (`10.0.0.10-80` or
+/// `10.0.0.10-10.0.1.20`) or the path of a file which lists one such specification per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetSet {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl TargetSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a target set from a list of specifications.
+    pub fn parse<S: AsRef<str>>(specs: &[S]) -> Result<Self, TargetError> {
+        let mut targets = Self::new();
+
+        for spec in specs {
+            targets.add_spec(spec.as_ref())?;
+        }
+
+        Ok(targets)
+    }
+
+    /// The hosts of the interface's network, without the network and broadcast addresses and the interface's own IP.
+    pub fn for_interface(interface: &NetworkInterface) -> Result<Self, InterfaceError> {
+        let Some(network) = interface.network() else {
+            return Err(InterfaceError::MissingIP);
+        };
+
+        let mut targets = Self::new();
+        targets.add_network(network);
+        targets.remove(network.ip());
+
+        Ok(targets)
+    }
+
+    /// Adds the addresses described by a single specification.
+    pub fn add_spec(&mut self, spec: &str) -> Result<(), TargetError> {
+        let spec = spec.trim();
+
+        if self.add_addresses(spec)? {
+            Ok(())
+        } else if Path::new(spec).is_file() {
+            self.add_file(Path::new(spec))
+        } else {
+            Err(TargetError::InvalidSpec(spec.to_string()))
+        }
+    }
+
+    /// Adds every specification listed in a file. Empty lines and lines starting with `#` are ignored.
+    ///
+    /// Note: A file may only list IPs, CIDRs and ranges, not other files, so that files cannot include each other
+    /// endlessly.
+    pub fn add_file(&mut self, path: &Path) -> Result<(), TargetError> {
+        let contents =
+            fs::read_to_string(path).map_err(|err| TargetError::Io(path.display().to_string(), err))?;
+
+        for line in contents.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if !self.add_addresses(line)? {
+                return Err(TargetError::InvalidSpec(line.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the addresses of an IP, CIDR or range specification. Returns `false` if the specification is none of them.
+    fn add_addresses(&mut self, spec: &str) -> Result<bool, TargetError> {
+        if let Ok(ip) = spec.parse::<Ipv4Addr>() {
+            self.add_range(ip, ip);
+        } else if let Ok(network) = spec.parse::<Ipv4Network>() {
+            self.add_network(network);
+        } else if let Some((start, end)) = parse_dash_range(spec) {
+            if start > end {
+                return Err(TargetError::InvalidSpec(spec.to_string()));
+            }
+            self.add_range(start, end);
+        } else {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Adds the hosts of a network. The network and broadcast addresses are left out, unless the prefix is so long
+    /// that the network has no others.
+    pub fn add_network(&mut self, network: Ipv4Network) {
+        let (start, end) = (u32::from(network.network()), u32::from(network.broadcast()));
+
+        if network.prefix() >= 31 {
+            self.insert(start, end);
+        } else {
+            self.insert(start + 1, end - 1);
+        }
+    }
+
+    /// Adds every address from `start` to `end`, inclusive.
+    pub fn add_range(&mut self, start: Ipv4Addr, end: Ipv4Addr) {
+        self.insert(u32::from(start), u32::from(end));
+    }
+
+    pub fn remove(&mut self, ip: Ipv4Addr) {
+        let ip = u32::from(ip);
+        self.subtract(ip, ip);
+    }
+
+    /// Removes every address of `other` from the set.
+    pub fn exclude(&mut self, other: &TargetSet) {
+        for &(start, end) in &other.ranges {
+            self.subtract(start, end);
+        }
+    }
+
+    /// Removes the addresses outside of the given network and returns them.
+    pub fn retain_network(&mut self, network: Ipv4Network) -> TargetSet {
+        let mut outside = self.clone();
+        outside.subtract(u32::from(network.network()), u32::from(network.broadcast()));
+
+        self.exclude(&outside);
+
+        outside
+    }
+
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        let ip = u32::from(ip);
+        self.ranges
+            .iter()
+            .any(|&(start, end)| start <= ip && ip <= end)
+    }
+
+    /// The number of addresses in the set.
+    pub fn len(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| u64::from(end - start) + 1)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Iterates over the addresses in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Ipv4Addr> + '_ {
+        self.ranges
+            .iter()
+            .flat_map(|&(start, end)| (start..=end).map(Ipv4Addr::from))
+    }
+
+    fn insert(&mut self, start: u32, end: u32) {
+        self.ranges.push((start, end));
+        self.ranges.sort_unstable();
+
+        // Merge overlapping and adjacent ranges
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(self.ranges.len());
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        self.ranges = merged;
+    }
+
+    fn subtract(&mut self, start: u32, end: u32) {
+        let mut remaining = Vec::with_capacity(self.ranges.len() + 1);
+
+        for &(range_start, range_end) in &self.ranges {
+            if range_end < start || range_start > end {
+                remaining.push((range_start, range_end));
+                continue;
+            }
+
+            if range_start < start {
+                remaining.push((range_start, start - 1));
+            }
+
+            if range_end > end {
+                remaining.push((end + 1, range_end));
+            }
+        }
+
+        self.ranges = remaining;
+    }
+}
+
+/// Parses `10.0.0.10-80`, where the end replaces the last octet, or `10.0.0.10-10.0.1.20`.
+fn parse_dash_range(spec: &str) -> Option<(Ipv4Addr, Ipv4Addr)> {
+    let (start, end) = spec.split_once('-')?;
+    let start: Ipv4Addr = start.trim().parse().ok()?;
+    let end = end.trim();
+
+    let end = if end.contains('.') {
+        end.parse().ok()?
+    } else {
+        let [a, b, c, _] = start.octets();
+        Ipv4Addr::new(a, b, c, end.parse().ok()?)
+    };
+
+    Some((start, end))
+}
+
+#[derive(Debug)]
+pub enum TargetError {
+    InvalidSpec(String),
+    Io(String, io::Error),
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::InvalidSpec(spec) => write!(f, "\"{}\" is not a valid IP, CIDR, range or file.", spec),
+            Self::Io(path, err) => write!(f, "Could not read targets from {}: {}", path, err),
+        }
+    }
+}
+
+impl std::error::Error for TargetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self {
+            Self::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    fn ip(addr: &str) -> u32 {
+        u32::from(addr.parse::<Ipv4Addr>().unwrap())
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_ranges() {
+        let targets =
+            TargetSet::parse(&["10.0.0.1-10", "10.0.0.5-20", "10.0.0.21", "10.0.0.30-40"]).unwrap();
+
+        assert_eq!(
+            targets.ranges,
+            vec![
+                (ip("10.0.0.1"), ip("10.0.0.21")),
+                (ip("10.0.0.30"), ip("10.0.0.40"))
+            ]
+        );
+        assert_eq!(targets.len(), 32);
+    }
+
+    #[test]
+    fn subtract_splits_and_trims_ranges() {
+        let mut targets = TargetSet::parse(&["10.0.0.1-100"]).unwrap();
+
+        targets.subtract(ip("10.0.0.10"), ip("10.0.0.19"));
+        targets.subtract(ip("10.0.0.90"), ip("10.0.0.200"));
+        targets.remove("10.0.0.1".parse().unwrap());
+
+        assert_eq!(
+            targets.ranges,
+            vec![
+                (ip("10.0.0.2"), ip("10.0.0.9")),
+                (ip("10.0.0.20"), ip("10.0.0.89"))
+            ]
+        );
+
+        targets.subtract(0, u32::MAX);
+        assert!(targets.is_empty());
+    }
+
+    #[test]
+    fn retain_network_returns_off_link_addresses() {
+        let mut targets = TargetSet::parse(&["10.0.0.250-10.0.1.5", "192.168.1.1"]).unwrap();
+
+        let outside = targets.retain_network("10.0.0.0/24".parse().unwrap());
+
+        assert_eq!(targets.ranges, vec![(ip("10.0.0.250"), ip("10.0.0.255"))]);
+        assert_eq!(
+            outside.ranges,
+            vec![
+                (ip("10.0.1.0"), ip("10.0.1.5")),
+                (ip("192.168.1.1"), ip("192.168.1.1"))
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_dash_ranges() {
+        assert_eq!(
+            parse_dash_range("10.0.0.10-80"),
+            Some((Ipv4Addr::new(10, 0, 0, 10), Ipv4Addr::new(10, 0, 0, 80)))
+        );
+        assert_eq!(
+            parse_dash_range("10.0.0.10 - 10.0.1.20"),
+            Some((Ipv4Addr::new(10, 0, 0, 10), Ipv4Addr::new(10, 0, 1, 20)))
+        );
+        assert_eq!(parse_dash_range("10.0.0.10-256"), None);
+        assert_eq!(parse_dash_range("10.0.0.10"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        assert!(matches!(
+            TargetSet::parse(&["10.0.0.80-10"]),
+            Err(TargetError::InvalidSpec(spec)) if spec == "10.0.0.80-10"
+        ));
+        assert!(matches!(
+            TargetSet::parse(&["10.0.0.10-300"]),
+            Err(TargetError::InvalidSpec(_))
+        ));
+    }
+
+    #[test]
+    fn reads_file_with_dash_in_path() {
+        let path = std::env::temp_dir().join(format!("arprender-targets-{}.txt", process::id()));
+        fs::write(&path, "# Lab hosts\n10.0.0.1\n\n10.0.0.10-12\n").unwrap();
+
+        let targets = TargetSet::parse(&[path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            targets.unwrap().ranges,
+            vec![
+                (ip("10.0.0.1"), ip("10.0.0.1")),
+                (ip("10.0.0.10"), ip("10.0.0.12"))
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_file_listing_a_file() {
+        let path = std::env::temp_dir().join(format!("arprender-targets-{}-self.txt", process::id()));
+        fs::write(&path, format!("10.0.0.1\n{}\n", path.display())).unwrap();
+
+        let targets = TargetSet::parse(&[path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(targets, Err(TargetError::InvalidSpec(spec)) if spec == path.display().to_string()));
+    }
+
+    #[test]
+    fn network_and_broadcast_are_only_kept_for_point_to_point_prefixes() {
+        let targets = TargetSet::parse(&["10.0.0.0/30", "10.0.1.0/31", "10.0.2.7/32"]).unwrap();
+
+        assert_eq!(
+            targets.iter().collect::<Vec<_>>(),
+            vec![
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 2),
+                Ipv4Addr::new(10, 0, 1, 0),
+                Ipv4Addr::new(10, 0, 1, 1),
+                Ipv4Addr::new(10, 0, 2, 7),
+            ]
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use std::net::Ipv4Addr;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use pnet::util::MacAddr;
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(author = "cr0mll")]
@@ -19,7 +22,10 @@ pub struct Args {
 pub enum Commands {
     #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
     /// Lists the available network interfaces.
-    Interfaces,
+    Interfaces {
+        #[command(flatten)]
+        output: OutputArgs
+    },
     
     #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
     /// Performs an ARP scan of the network.
@@ -27,9 +33,45 @@ pub enum Commands {
         /// The network interface to use for the scan. 
         interface: String,
 
-        /// A timeout (in seconds) after which to cease awaiting responses to the scan.
+        /// The addresses to scan, given as IPs, CIDRs, ranges such as 10.0.0.10-80, or files listing them. Defaults to the interface's network.
+        targets: Vec<String>,
+
+        /// Addresses to leave out of the scan, in the same formats as the targets.
+        #[arg(short = 'x', long, value_delimiter = ',')]
+        exclude: Vec<String>,
+
+        /// A timeout (in seconds) after which to cease awaiting responses once the last request has been sent.
         #[arg(short, long, required = false, default_value = "10")]
-        timeout: u16
+        timeout: u16,
+
+        /// The maximum number of requests to send per second. Unlimited by default.
+        #[arg(short, long)]
+        rate: Option<NonZeroU32>,
+
+        /// The number of times to resend a request to targets which have not answered.
+        #[arg(long, default_value = "0")]
+        retries: u32,
+
+        /// The delay (in milliseconds) between two rounds of requests.
+        #[arg(long, default_value = "1000")]
+        retry_interval: u64,
+
+        #[command(flatten)]
+        output: OutputArgs
+    },
+
+    #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
+    /// Passively discovers hosts from the ARP traffic on the network, without transmitting anything.
+    Listen {
+        /// The network interface to listen on.
+        interface: String,
+
+        /// How long (in seconds) to listen for. Listens until interrupted by default.
+        #[arg(short, long)]
+        duration: Option<u64>,
+
+        #[command(flatten)]
+        output: OutputArgs
     },
 
     #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
@@ -43,26 +85,194 @@ pub enum Commands {
 
         #[arg(short, long, required = false, default_value = "10")]
         /// A timeout (in seconds) after which to cease waiting for an ARP response.
-        timeout: u16
+        timeout: u16,
+
+        #[arg(short, long, required = false)]
+        /// A MAC address the host is already known by. The request is then sent directly to it instead of being broadcast, in order to check that the host is still there.
+        mac: Option<MacAddr>,
+
+        #[command(flatten)]
+        output: OutputArgs
     },
 
     #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
     /// Impersonates the target host by tricking all devices on the network to forward all traffic intended for the target to you.
-    Impersonate {
-        /// The IP address of the target.
+    Impersonate(ImpersonateArgs),
+
+    #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
+    /// Intercepts the traffic between a victim and its gateway by poisoning the ARP caches of both.
+    Mitm {
+        /// The IP address of the victim.
         #[arg(required = true)]
-        target: Ipv4Addr,
+        victim: Ipv4Addr,
 
         /// The interface to use for the attack.
         #[arg(required = true)]
         interface: String,
 
+        /// The IP address of the gateway. Defaults to the interface's default gateway.
+        #[arg(long, short)]
+        gateway: Option<Ipv4Addr>,
+
+        /// The interval (in seconds) at which to repeat the forged replies in order to keep the caches poisoned.
+        #[arg(default_value = "2", long, short, value_parser = clap::value_parser!(u16).range(1..))]
+        period: u16,
+
+        /// A timeout (in seconds) after which to give up resolving the victim and the gateway.
+        #[arg(default_value = "5", long, short)]
+        timeout: u16,
+
         #[arg(default_value = "false", long, short)]
-        /// Attempt a stealthier, but less reliable, form of the attack.
-        stealthy: bool,
+        /// Enable IP forwarding for the duration of the attack, so that intercepted packets are passed on to their destination.
+        forward: bool,
 
-        /// The interval (in seconds) at which to repeat a stealthy attack in order to ensure it remains effective.
-        #[arg(default_value = "5", long, short, requires = "stealthy")]
-        period: u16
+        #[arg(default_value = "false", long, conflicts_with = "forward")]
+        /// Pass intercepted packets on to their destination from userspace instead of relying on kernel IP forwarding.
+        relay: bool
     },
-}
\ No newline at end of file
+
+    #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
+    /// Restores the ARP caches of the network after an attack by announcing the real MAC addresses of the given hosts.
+    Heal {
+        /// The interface to use for restoring the caches.
+        interface: String,
+
+        /// The IP addresses of the hosts which were impersonated.
+        #[arg(required = true)]
+        targets: Vec<Ipv4Addr>,
+
+        /// A timeout (in seconds) for the scan which finds the hosts on the network.
+        #[arg(short, long, required = false, default_value = "10")]
+        timeout: u16
+    },
+
+    #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
+    /// Sends gratuitous ARP announcements, which claim an IP address for a MAC address.
+    Announce {
+        /// The IP address to announce.
+        #[arg(required = true)]
+        address: Ipv4Addr,
+
+        /// The interface to use for the announcements.
+        #[arg(required = true)]
+        interface: String,
+
+        /// The MAC address to announce for the IP address. Defaults to the interface's MAC address.
+        #[arg(long, short)]
+        mac: Option<MacAddr>,
+
+        /// The number of announcements to send.
+        #[arg(default_value = "3", long, short)]
+        count: u32,
+
+        /// The interval (in seconds) between consecutive announcements.
+        #[arg(default_value = "1", long, short)]
+        interval: u16,
+
+        #[arg(default_value = "false", long, short)]
+        /// Send the announcements as ARP replies instead of ARP requests.
+        reply: bool
+    },
+
+    #[command(help_template = "Arprender v{version}\n{about-with-newline}cr0mll (C) cr0mll@protonmail.com \n\n{usage-heading} {usage}\n\n{all-args}")]
+    /// Checks whether an IP address is already in use, following the address conflict detection of RFC 5227.
+    Probe {
+        /// The IP address to probe.
+        #[arg(required = true)]
+        address: Ipv4Addr,
+
+        /// The interface to use for the probes.
+        #[arg(required = true)]
+        interface: String,
+
+        #[arg(default_value = "false", long)]
+        /// Do not announce the address once it has been found to be free.
+        no_announce: bool
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ImpersonateArgs {
+    /// The addresses to impersonate, given as comma-separated IPs, CIDRs, ranges or files listing them.
+    #[arg(required = true, value_delimiter = ',', num_args = 1)]
+    pub targets: Vec<String>,
+
+    /// The interface to use for the attack.
+    #[arg(required = true)]
+    pub interface: String,
+
+    #[arg(default_value = "false", long, short)]
+    /// Attempt a stealthier, but less reliable, form of the attack.
+    pub stealthy: bool,
+
+    /// A MAC address to map the targets to instead of the interface's, such as that of a separate capture device on the same network.
+    #[arg(long, conflicts_with_all = ["forward", "relay"])]
+    pub as_mac: Option<MacAddr>,
+
+    /// The interval (in seconds) at which to repeat the attack in order to ensure it remains effective.
+    #[arg(default_value = "5", long, short, value_parser = clap::value_parser!(u16).range(1..))]
+    pub period: u16,
+
+    /// Up to this many milliseconds of random delay to add to each period, so that the attack is less regular.
+    #[arg(default_value = "0", long, short)]
+    pub jitter: u64,
+
+    /// The maximum number of rounds to send. Unlimited by default.
+    #[arg(long, short)]
+    pub count: Option<u64>,
+
+    /// How long (in seconds) to run the attack for. Runs until interrupted by default.
+    #[arg(long, short)]
+    pub duration: Option<u64>,
+
+    /// A timeout (in seconds) for the scan which finds the hosts on the network before the attack.
+    #[arg(long, default_value = "10", conflicts_with = "stealthy")]
+    pub scan_timeout: u16,
+
+    /// A scan saved with `scan -o json` or `scan -o csv` to use instead of scanning the network before the attack.
+    #[arg(long, conflicts_with_all = ["stealthy", "scan_timeout"])]
+    pub hosts_file: Option<PathBuf>,
+
+    /// The hosts to send forged replies to, given as IPs, CIDRs, ranges or files listing them. Defaults to every host found by the scan.
+    #[arg(long, value_delimiter = ',', conflicts_with = "stealthy")]
+    pub victims: Vec<String>,
+
+    /// Hosts which must not receive forged replies, in the same formats as the victims.
+    #[arg(short = 'x', long, value_delimiter = ',', conflicts_with = "stealthy")]
+    pub exclude: Vec<String>,
+
+    #[arg(default_value = "false", long, short)]
+    /// Enable IP forwarding for the duration of the attack, so that intercepted packets are passed on to the targets.
+    pub forward: bool,
+
+    #[arg(default_value = "false", long, conflicts_with = "forward")]
+    /// Pass intercepted packets on to the targets from userspace instead of relying on kernel IP forwarding.
+    pub relay: bool
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// The format in which to print the results.
+    #[arg(short, long, value_enum, default_value = "table")]
+    pub output: OutputFormat,
+
+    /// A file to write the results to instead of the standard output.
+    #[arg(long)]
+    pub output_file: Option<PathBuf>,
+
+    /// An IEEE oui.txt or Wireshark manuf file with which to identify MAC address vendors, in addition to the built-in list.
+    #[arg(long)]
+    pub oui_file: Option<PathBuf>
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A human-readable table.
+    Table,
+    /// A single JSON array.
+    Json,
+    /// One JSON object per line.
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv
+}
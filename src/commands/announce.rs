@@ -0,0 +1,55 @@
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::Duration;
+
+use arprender::arp;
+use pnet::util::MacAddr;
+
+use super::CommandResult;
+
+pub fn announce(
+    interface: String,
+    address: Ipv4Addr,
+    mac: Option<MacAddr>,
+    count: u32,
+    interval: u16,
+    reply: bool,
+) -> CommandResult {
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+
+    let Some(mac) = mac.or(interface.mac()) else {
+        return Err(arp::nic::InterfaceError::MissingMAC.into());
+    };
+
+    let mut session = arp::ArpSession::new(&interface)?;
+
+    let interval = Duration::from_secs(interval.into());
+    let style = if reply {
+        arp::AnnouncementStyle::Reply
+    } else {
+        arp::AnnouncementStyle::Request
+    };
+
+    println!(
+        "Announcing {} at {} using gratuitous ARP {}...",
+        address,
+        mac,
+        if reply { "replies" } else { "requests" }
+    );
+
+    for i in 0..count {
+        // Wait between announcements, but not after the last one.
+        if i > 0 {
+            thread::sleep(interval);
+        }
+
+        match style {
+            arp::AnnouncementStyle::Request => session.send_gratuitous_request(address, Some(mac))?,
+            arp::AnnouncementStyle::Reply => session.send_gratuitous_reply(address, Some(mac))?,
+        }
+    }
+
+    println!("Sent {} announcement(s).", count);
+
+    Ok(())
+}
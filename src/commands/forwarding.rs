@@ -0,0 +1,116 @@
+use std::error::Error;
+use std::thread::{self, JoinHandle};
+
+use arprender::arp::nic::{InterfaceError, NetworkInterface};
+use arprender::arp::{CancelHandle, Peer, Relay};
+use arprender::forwarding::{self, ForwardingGuard};
+use tabled::settings::{Alignment, Settings};
+
+use super::CommandResult;
+
+/// Prepares the kernel for passing on intercepted traffic, enabling IP forwarding if requested, and warns if the
+/// traffic is going to be dropped or delivered twice instead.
+pub fn prepare_forwarding(
+    interface: &str,
+    forward: bool,
+    relay: bool,
+) -> Result<ForwardingGuard, Box<dyn Error>> {
+    // The relay takes the place of the kernel, so the settings are left alone
+    if relay {
+        if forwarding::is_forwarding_enabled()? {
+            eprintln!("Warning: IP forwarding is enabled, so relayed packets will be delivered twice.");
+        }
+
+        return Ok(ForwardingGuard::default());
+    }
+
+    let mut guard = ForwardingGuard::default();
+    if forward {
+        guard.enable_forwarding()?;
+    }
+
+    let forwarding_enabled = forwarding::is_forwarding_enabled()?;
+    if forwarding_enabled {
+        match guard.disable_redirects(interface) {
+            Ok(()) => {}
+            // Forwarding was already on, so an attack which cannot change the settings still works without this
+            Err(err) if !forward => {
+                eprintln!("Warning: {}. ICMP redirects may reveal the attack to the victims.", err)
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    if !forwarding_enabled {
+        eprintln!("Warning: IP forwarding is disabled, so intercepted packets will be dropped. Use --forward or --relay to pass them on.");
+    } else if !guard.is_empty() {
+        println!("IP forwarding is enabled and ICMP redirects are disabled on {} until the attack ends.", interface);
+    }
+
+    Ok(guard)
+}
+
+/// A userspace relay running on its own thread.
+pub struct RelayThread {
+    cancel: CancelHandle,
+    handle: JoinHandle<Result<Relay, InterfaceError>>,
+}
+
+impl RelayThread {
+    /// Starts relaying the traffic intercepted for the peers and, if given, for the gateway.
+    pub fn start(
+        interface: &NetworkInterface,
+        peers: &[Peer],
+        gateway: Option<Peer>,
+    ) -> Result<Self, InterfaceError> {
+        let mut relay = Relay::new(interface)?;
+
+        for peer in peers {
+            relay.add_peer(*peer);
+        }
+
+        if let Some(gateway) = gateway {
+            relay.set_gateway(gateway);
+        }
+
+        let cancel = relay.cancel_handle();
+        let handle = thread::spawn(move || {
+            relay.run(None)?;
+            Ok(relay)
+        });
+
+        Ok(Self { cancel, handle })
+    }
+
+    /// Stops the relay and prints the traffic it relayed for each flow.
+    pub fn stop(self) -> CommandResult {
+        self.cancel.cancel();
+        let relay = self.handle.join().map_err(|_| "The relay thread panicked.")??;
+
+        let flows = relay.flows();
+        if flows.is_empty() {
+            println!("No traffic was relayed.");
+            return Ok(());
+        }
+
+        // Construct output table
+        let table_config = Settings::default().with(Alignment::center());
+        let mut flows_table = tabled::builder::Builder::new();
+        flows_table.push_record(["Flow", "Frames", "Bytes", "Dropped"]);
+
+        for (flow, stats) in &flows {
+            flows_table.push_record([
+                flow.to_string(),
+                stats.frames.to_string(),
+                stats.bytes.to_string(),
+                stats.dropped.to_string(),
+            ]);
+        }
+
+        // Print output
+        println!("Relayed traffic:");
+        println!("{}", flows_table.build().with(table_config));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use arprender::arp;
+use arprender::arp::Peer;
+
+use super::CommandResult;
+
+pub fn heal(interface: String, targets: Vec<Ipv4Addr>, timeout: u16) -> CommandResult {
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+    let mut session = arp::ArpSession::new(&interface)?;
+
+    // Every host on the network may have been poisoned, and the scan also yields the targets' real MAC addresses
+    println!("Launching ARP scan using timeout {} seconds...", timeout);
+    let options = arp::ScanOptions {
+        timeout: Duration::from_secs(timeout.into()),
+        ..Default::default()
+    };
+    let hosts = session.scan(&arp::TargetSet::for_interface(&interface)?, &options)?;
+    println!("Identified {} host(s).", hosts.len());
+
+    let mut restorable = Vec::new();
+    for target in targets {
+        match hosts.iter().find(|host| host.ip == target) {
+            Some(host) => restorable.push(Peer { ip: target, mac: host.mac }),
+            None => eprintln!("Warning: {} did not answer the scan, so its real MAC address is unknown.", target),
+        }
+    }
+
+    if restorable.is_empty() {
+        return Err("None of the targets could be restored!".into());
+    }
+
+    for target in &restorable {
+        println!("Restoring {} in the ARP caches of the network...", target);
+    }
+    session.restore_hosts(&restorable, &hosts)?;
+
+    Ok(())
+}
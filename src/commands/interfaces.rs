@@ -1,29 +1,62 @@
-use tabled::settings::{Alignment, Settings};
-use crate::arp;
+use arprender::arp;
+use arprender::arp::MacFlag;
+use serde::Serialize;
 
-pub fn interfaces() {
-    let interfaces = arp::nic::get_interfaces();
+use super::output::{cell, field, join, load_oui_database, vendor_cell, write_records, Record};
+use super::CommandResult;
+use crate::cli::OutputArgs;
 
-    // Prepare pretty formatting
-    let table_config = Settings::default().with(Alignment::center());
-    let mut interfaces_table = tabled::builder::Builder::new();
-    interfaces_table.push_record(["Name", "MAC Address", "IP Address"]);
+#[derive(Serialize)]
+struct InterfaceRecord {
+    name: String,
+    mac: Option<String>,
+    vendor: Option<String>,
+    mac_flags: Vec<MacFlag>,
+    ip: Option<String>,
+}
 
-    for interface in interfaces {
-        let mac_str = match interface.mac() {
-            Some(mac) => mac.to_string(),
-            None => "None".to_string(),
-        };
-        let ip_str = match interface.ipv4_address() {
-            Some(ip) => ip.to_string(),
-            None => "None".to_string(),
+impl Record for InterfaceRecord {
+    const TITLES: &'static [&'static str] = &["Name", "MAC Address", "Vendor", "IP Address"];
+
+    const FIELDS: &'static [&'static str] = &["name", "mac", "vendor", "mac_flags", "ip"];
+
+    fn cells(&self) -> Vec<String> {
+        // Interfaces without a MAC address have no vendor to speak of
+        let vendor = match &self.mac {
+            Some(_) => vendor_cell(self.vendor.as_deref(), &self.mac_flags),
+            None => cell(None::<&str>),
         };
 
-        interfaces_table.push_record([interface.name(), &mac_str, &ip_str]);
+        vec![self.name.clone(), cell(self.mac.as_ref()), vendor, cell(self.ip.as_ref())]
     }
 
-    println!(
-        "{}",
-        interfaces_table.build().with(table_config).to_string()
-    );
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            field(self.mac.as_ref()),
+            field(self.vendor.as_ref()),
+            join(&self.mac_flags.iter().map(MacFlag::name).collect::<Vec<_>>(), ";"),
+            field(self.ip.as_ref()),
+        ]
+    }
+}
+
+pub fn interfaces(output: OutputArgs) -> CommandResult {
+    let vendors = load_oui_database(&output)?;
+
+    let interfaces: Vec<InterfaceRecord> = arp::nic::get_interfaces()
+        .into_iter()
+        .map(|interface| InterfaceRecord {
+            name: interface.name().to_string(),
+            mac: interface.mac().map(|mac| mac.to_string()),
+            vendor: interface
+                .mac()
+                .and_then(|mac| vendors.lookup(mac))
+                .map(str::to_string),
+            mac_flags: interface.mac().map(MacFlag::for_mac).unwrap_or_default(),
+            ip: interface.ipv4_address().map(|ip| ip.to_string()),
+        })
+        .collect();
+
+    write_records(&interfaces, &output)
 }
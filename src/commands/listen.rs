@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use arprender::arp;
+
+use super::output::{load_oui_database, write_records};
+use super::CommandResult;
+use crate::cli::{OutputArgs, OutputFormat};
+
+pub fn listen(interface: String, duration: Option<u64>, output: OutputArgs) -> CommandResult {
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+    let vendors = load_oui_database(&output)?;
+    let mut listener = arp::ArpListener::new(&interface)?;
+
+    // Stop listening, rather than exiting, on Ctrl-C so that the hosts seen so far still get printed
+    let cancel = listener.cancel_handle();
+    ctrlc::set_handler(move || cancel.cancel())?;
+
+    match duration {
+        Some(duration) => eprintln!("Listening for ARP traffic for {} seconds...", duration),
+        None => eprintln!("Listening for ARP traffic until interrupted..."),
+    }
+    let mut hosts = listener.listen(duration.map(Duration::from_secs))?;
+
+    for host in &mut hosts {
+        host.identify_vendor(&vendors);
+    }
+
+    if output.output == OutputFormat::Table {
+        eprintln!("Observed hosts:");
+    }
+
+    write_records(&hosts, &output)
+}
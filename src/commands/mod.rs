@@ -1,12 +1,56 @@
+use std::error::Error;
+
+use arprender::arp::nic::NetworkInterface;
+
+mod forwarding;
+mod output;
+mod schedule;
+
 mod interfaces;
 pub use interfaces::interfaces;
 
 mod scan;
 pub use scan::scan;
 
+mod listen;
+pub use listen::listen;
+
 mod resolve;
 pub use resolve::resolve;
 
-#[macro_use]
 mod impersonate;
-pub use impersonate::impersonate;
\ No newline at end of file
+pub use impersonate::impersonate;
+
+mod mitm;
+pub use mitm::mitm;
+
+mod heal;
+pub use heal::heal;
+
+mod announce;
+pub use announce::announce;
+
+mod probe;
+pub use probe::probe;
+
+/// The result of running a command. Errors are reported to the user by `main`.
+pub type CommandResult = Result<(), Box<dyn Error>>;
+
+/// Warns that `count` addresses of the given kind are skipped because they cannot be reached on the
+/// interface's network.
+fn warn_off_link(interface: &NetworkInterface, count: u64, kind: &str) {
+    let Some(network) = interface.network() else {
+        return;
+    };
+
+    if count > 0 {
+        eprintln!(
+            "Warning: skipping {} {}(s) which cannot be reached on {}'s network {}/{}.",
+            count,
+            kind,
+            interface.name(),
+            network.network(),
+            network.prefix()
+        );
+    }
+}
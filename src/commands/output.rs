@@ -0,0 +1,176 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+use arprender::arp::{Host, MacFlag, OuiDatabase};
+use serde::Serialize;
+use tabled::settings::{Alignment, Settings};
+
+use super::CommandResult;
+use crate::cli::{OutputArgs, OutputFormat};
+
+/// A result which can be printed in any of the output formats.
+///
+/// JSON and JSON Lines output use the record's `Serialize` implementation, whereas tables and CSV are built from
+/// the flat columns below.
+pub trait Record: Serialize {
+    /// The column headings of the table.
+    const TITLES: &'static [&'static str];
+
+    /// The column names of the CSV header.
+    const FIELDS: &'static [&'static str];
+
+    /// The cells of the record's table row.
+    fn cells(&self) -> Vec<String>;
+
+    /// The values of the record's CSV row.
+    fn fields(&self) -> Vec<String>;
+}
+
+/// Writes the records to the standard output, or to the file given in the output arguments.
+pub fn write_records<R: Record>(records: &[R], args: &OutputArgs) -> CommandResult {
+    let mut out: Box<dyn Write> = match &args.output_file {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    match args.output {
+        OutputFormat::Table => {
+            // Construct output table
+            let table_config = Settings::default().with(Alignment::center());
+            let mut table = tabled::builder::Builder::new();
+            table.push_record(R::TITLES.iter().copied());
+
+            for record in records {
+                table.push_record(record.cells());
+            }
+
+            writeln!(out, "{}", table.build().with(table_config))?;
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut out, record)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(R::FIELDS)?;
+
+            for record in records {
+                writer.write_record(record.fields())?;
+            }
+
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats an optional value for a table cell.
+pub fn cell<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "None".to_string(),
+    }
+}
+
+/// Formats an optional value for a CSV field, leaving it empty if there is none.
+pub fn field<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Loads the built-in vendor list, extended with the OUI file given in the output arguments.
+pub fn load_oui_database(args: &OutputArgs) -> Result<OuiDatabase, Box<dyn Error>> {
+    let mut database = OuiDatabase::builtin();
+
+    if let Some(path) = &args.oui_file {
+        let loaded = OuiDatabase::from_file(path)
+            .map_err(|err| format!("Could not read OUI file {}: {}", path.display(), err))?;
+
+        if loaded.is_empty() {
+            eprintln!("Warning: no OUI entries found in {}.", path.display());
+        }
+
+        database.extend(loaded);
+    }
+
+    Ok(database)
+}
+
+/// Formats a vendor for a table cell, followed by any flags so that unusual addresses stand out.
+pub fn vendor_cell(vendor: Option<&str>, flags: &[MacFlag]) -> String {
+    let vendor = vendor.unwrap_or("Unknown");
+
+    if flags.is_empty() {
+        return vendor.to_string();
+    }
+
+    format!("{} ({})", vendor, join(flags, ", "))
+}
+
+/// Joins the values for a single CSV field.
+pub fn join<T: ToString>(values: &[T], separator: &str) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+pub fn format_latency(latency: Duration) -> String {
+    format!("{:.2} ms", latency.as_secs_f64() * 1000.0)
+}
+
+impl Record for Host {
+    const TITLES: &'static [&'static str] = &["IP Address", "MAC Address", "Vendor", "Latency"];
+
+    const FIELDS: &'static [&'static str] = &[
+        "ip",
+        "mac",
+        "vendor",
+        "mac_flags",
+        "interface",
+        "first_seen",
+        "last_seen",
+        "latency_ms",
+        "replies",
+        "extra_macs",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.ip.to_string(),
+            self.mac.to_string(),
+            vendor_cell(self.vendor.as_deref(), &self.mac_flags),
+            cell(self.latency.map(format_latency)),
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let unix_secs = |time: std::time::SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or(Duration::ZERO)
+                .as_secs_f64()
+        };
+
+        vec![
+            self.ip.to_string(),
+            self.mac.to_string(),
+            field(self.vendor.as_ref()),
+            join(&self.mac_flags.iter().map(MacFlag::name).collect::<Vec<_>>(), ";"),
+            self.interface.clone(),
+            unix_secs(self.first_seen).to_string(),
+            unix_secs(self.last_seen).to_string(),
+            field(self.latency.map(|latency| latency.as_secs_f64() * 1000.0)),
+            self.replies.to_string(),
+            join(&self.extra_macs, ";"),
+        ]
+    }
+}
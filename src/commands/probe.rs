@@ -0,0 +1,31 @@
+use std::net::Ipv4Addr;
+
+use arprender::arp;
+use arprender::arp::ProbeOutcome;
+
+use super::CommandResult;
+
+pub fn probe(interface: String, address: Ipv4Addr, no_announce: bool) -> CommandResult {
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+    let mut session = arp::ArpSession::new(&interface)?;
+
+    println!("Probing {} for conflicts...", address);
+    match session.probe(address)? {
+        ProbeOutcome::Available => {
+            println!("No other host answered or probed {}.", address);
+        }
+        ProbeOutcome::InUse(mac) => {
+            return Err(format!("Address {} is already in use by {}!", address, mac).into());
+        }
+        ProbeOutcome::Contested(mac) => {
+            return Err(format!("Host {} is also probing for {}!", mac, address).into());
+        }
+    }
+
+    if !no_announce {
+        println!("Announcing {}...", address);
+        session.announce_claim(address)?;
+    }
+
+    Ok(())
+}
@@ -1,27 +1,102 @@
 use std::net::Ipv4Addr;
-use std::time::Duration;
-
-use crate::arp;
-
-pub fn resolve(interface: String, address: Ipv4Addr, timeout: u16) {
-    match arp::nic::get_interface_by_name(&interface) {
-        Ok(interface) => match arp::resolve_mac(interface, address, Duration::from_secs(timeout.into())) {
-            Ok(mac) => match mac {
-                Some(mac) => {
-                    println!("IP {} has MAC address {}", address, mac);
-                }
-                None => {
-                    println!("Failed to resolve address!");
-                }
-            },
-            Err(err) => {
-                println!("{}", err.to_string());
-                std::process::exit(1);
-            }
-        },
-        Err(err) => {
-            println!("{}", err.to_string());
-            std::process::exit(1);
+use std::time::{Duration, Instant};
+
+use arprender::arp;
+use arprender::arp::MacFlag;
+use pnet::util::MacAddr;
+use serde::Serialize;
+
+use super::output::{
+    cell, field, format_latency, join, load_oui_database, vendor_cell, write_records, Record,
+};
+use super::CommandResult;
+use crate::cli::{OutputArgs, OutputFormat};
+
+#[derive(Serialize)]
+struct ResolveRecord {
+    ip: Ipv4Addr,
+    /// The MAC address which answered, if any.
+    mac: Option<MacAddr>,
+    vendor: Option<String>,
+    mac_flags: Vec<MacFlag>,
+    latency_ms: Option<f64>,
+}
+
+impl Record for ResolveRecord {
+    const TITLES: &'static [&'static str] = &["IP Address", "MAC Address", "Vendor", "Latency"];
+
+    const FIELDS: &'static [&'static str] = &["ip", "mac", "vendor", "mac_flags", "latency_ms"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.ip.to_string(),
+            cell(self.mac),
+            vendor_cell(self.vendor.as_deref(), &self.mac_flags),
+            cell(self.latency_ms.map(|ms| format_latency(Duration::from_secs_f64(ms / 1000.0)))),
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.ip.to_string(),
+            field(self.mac),
+            field(self.vendor.as_ref()),
+            join(&self.mac_flags.iter().map(MacFlag::name).collect::<Vec<_>>(), ";"),
+            field(self.latency_ms),
+        ]
+    }
+}
+
+pub fn resolve(
+    interface: String,
+    address: Ipv4Addr,
+    timeout: u16,
+    mac: Option<MacAddr>,
+    output: OutputArgs,
+) -> CommandResult {
+    let timeout = Duration::from_secs(timeout.into());
+    let vendors = load_oui_database(&output)?;
+
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+    let mut session = arp::ArpSession::new(&interface)?;
+
+    let start = Instant::now();
+
+    // A known MAC address only needs to be confirmed
+    let resolved = match mac {
+        Some(mac) => session.verify(address, mac, timeout)?.then_some(mac),
+        None => session.resolve(address, timeout)?,
+    };
+
+    let record = ResolveRecord {
+        ip: address,
+        mac: resolved,
+        vendor: resolved.and_then(|mac| vendors.lookup(mac)).map(str::to_string),
+        mac_flags: resolved.map(MacFlag::for_mac).unwrap_or_default(),
+        latency_ms: resolved.map(|_| start.elapsed().as_secs_f64() * 1000.0),
+    };
+
+    // Tables are reserved for listings, a single address is reported in prose
+    if output.output == OutputFormat::Table && output.output_file.is_none() {
+        match (mac, resolved) {
+            (Some(mac), Some(_)) => println!(
+                "IP {} is still at MAC address {} [{}]",
+                address,
+                mac,
+                vendor_cell(record.vendor.as_deref(), &record.mac_flags)
+            ),
+            (Some(mac), None) => println!("IP {} did not answer at MAC address {}!", address, mac),
+            (None, Some(mac)) => println!(
+                "IP {} has MAC address {} [{}]",
+                address,
+                mac,
+                vendor_cell(record.vendor.as_deref(), &record.mac_flags)
+            ),
+            (None, None) => println!("Failed to resolve address!"),
         }
+
+        return Ok(());
     }
-}
\ No newline at end of file
+
+    write_records(&[record], &output)
+}
@@ -1,39 +1,47 @@
-use std::time::Duration;
-
-use tabled::settings::{Alignment, Settings};
-
-use crate::arp;
-
-pub fn scan(interface: String, period: u16) {
-    match arp::nic::get_interface_by_name(&interface) {
-        Ok(interface) => {
-            println!("Conducting ARP scan...");
-            match arp::arp_scan(&interface, Duration::from_secs(period.into())) {
-                Ok(hosts) => {
-                    // Construct output table
-                    let table_config = Settings::default().with(Alignment::center());
-                    let mut interfaces_table = tabled::builder::Builder::new();
-                    interfaces_table.push_record(["IP Address", "MAC Address"]);
-
-                    for host in hosts {
-                        interfaces_table.push_record([host.0.to_string(), host.1.to_string()]);
-                    }
-
-                    // Print output
-                    println!("Identified hosts:");
-                    println!(
-                        "{}",
-                        interfaces_table.build().with(table_config).to_string()
-                    );
-                }
-                Err(err) => {
-                    println!("{}", err.to_string());
-                }
-            }
-        }
-        Err(err) => {
-            println!("{}", err.to_string());
-            std::process::exit(1);
-        }
+use arprender::arp;
+use arprender::arp::{ScanOptions, TargetSet};
+
+use super::output::{load_oui_database, write_records};
+use super::{warn_off_link, CommandResult};
+use crate::cli::{OutputArgs, OutputFormat};
+
+pub fn scan(
+    interface: String,
+    targets: Vec<String>,
+    exclude: Vec<String>,
+    options: ScanOptions,
+    output: OutputArgs,
+) -> CommandResult {
+    let interface = arp::nic::get_interface_by_name(&interface)?;
+    let vendors = load_oui_database(&output)?;
+
+    let Some(network) = interface.network() else {
+        return Err(arp::nic::InterfaceError::MissingIP.into());
+    };
+
+    let mut targets = if targets.is_empty() {
+        TargetSet::for_interface(&interface)?
+    } else {
+        TargetSet::parse(&targets)?
+    };
+    targets.exclude(&TargetSet::parse(&exclude)?);
+
+    // Hosts outside of the interface's network cannot answer ARP requests sent on it
+    let off_link = targets.retain_network(network);
+    warn_off_link(&interface, off_link.len(), "target");
+
+    let mut session = arp::ArpSession::new(&interface)?;
+
+    eprintln!("Conducting ARP scan of {} address(es)...", targets.len());
+    let mut hosts = session.scan(&targets, &options)?;
+
+    for host in &mut hosts {
+        host.identify_vendor(&vendors);
     }
+
+    if output.output == OutputFormat::Table {
+        eprintln!("Identified hosts:");
+    }
+
+    write_records(&hosts, &output)
 }
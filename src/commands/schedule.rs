@@ -0,0 +1,17 @@
+use arprender::scheduler::{ScheduleReport, StopReason};
+
+/// Prints how many rounds an attack sent and why it ended.
+pub fn print_report(report: &ScheduleReport) {
+    let reason = match report.stop_reason {
+        StopReason::Count => "the maximum count was reached",
+        StopReason::Duration => "the maximum duration passed",
+        StopReason::Cancelled => "it was interrupted",
+    };
+
+    println!(
+        "Sent {} round(s) in {:.1} seconds before {}.",
+        report.rounds,
+        report.elapsed.as_secs_f64(),
+        reason
+    );
+}
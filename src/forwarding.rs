@@ -0,0 +1,102 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The kernel setting which controls whether IPv4 packets are routed between interfaces.
+const IP_FORWARD: &str = "/proc/sys/net/ipv4/ip_forward";
+
+/// The kernel setting which controls whether ICMP redirects are sent for packets routed back out of the interface.
+fn send_redirects(interface: &str) -> PathBuf {
+    PathBuf::from(format!("/proc/sys/net/ipv4/conf/{}/send_redirects", interface))
+}
+
+/// Whether the kernel currently forwards IPv4 packets.
+pub fn is_forwarding_enabled() -> io::Result<bool> {
+    Ok(read_setting(Path::new(IP_FORWARD))? != "0")
+}
+
+/// Kernel settings changed for the duration of an attack. The original values are put back when the guard is
+/// restored or dropped.
+///
+/// Intercepted packets are only passed on to their real destination if IP forwarding is enabled. When it is, the
+/// kernel notices that it routes them back out of the interface they came in on and tells the victims about the
+/// better route with ICMP redirects, so those are turned off.
+#[derive(Debug, Default)]
+pub struct ForwardingGuard {
+    /// The settings which were changed along with their original values, in the order they were changed.
+    changed: Vec<(PathBuf, String)>,
+}
+
+impl ForwardingGuard {
+    /// Optionally enables IP forwarding and, if forwarding is enabled, disables ICMP redirects on the interface.
+    pub fn new(interface: &str, enable_forwarding: bool) -> io::Result<Self> {
+        let mut guard = Self::default();
+
+        if enable_forwarding {
+            guard.enable_forwarding()?;
+        }
+
+        if is_forwarding_enabled()? {
+            guard.disable_redirects(interface)?;
+        }
+
+        Ok(guard)
+    }
+
+    pub fn enable_forwarding(&mut self) -> io::Result<()> {
+        self.set(PathBuf::from(IP_FORWARD), "1")
+    }
+
+    /// Stops the kernel from sending ICMP redirects for packets routed back out of the interface.
+    ///
+    /// Note: The kernel sends redirects if they are enabled either for the interface or for `all`, so both are
+    /// disabled.
+    pub fn disable_redirects(&mut self, interface: &str) -> io::Result<()> {
+        self.set(send_redirects("all"), "0")?;
+        self.set(send_redirects(interface), "0")
+    }
+
+    /// Whether any setting was changed.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+
+    /// Puts back the original values, in the reverse order in which they were changed.
+    pub fn restore(&mut self) -> io::Result<()> {
+        while let Some((path, original)) = self.changed.pop() {
+            write_setting(&path, &original)?;
+        }
+
+        Ok(())
+    }
+
+    /// Changes a setting, remembering its original value if it differs.
+    fn set(&mut self, path: PathBuf, value: &str) -> io::Result<()> {
+        let original = read_setting(&path)?;
+
+        if original != value {
+            write_setting(&path, value)?;
+            self.changed.push((path, original));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ForwardingGuard {
+    fn drop(&mut self) {
+        // Errors can only be reported by calling restore beforehand
+        let _ = self.restore();
+    }
+}
+
+fn read_setting(path: &Path) -> io::Result<String> {
+    fs::read_to_string(path)
+        .map(|value| value.trim().to_string())
+        .map_err(|err| io::Error::new(err.kind(), format!("Could not read {}: {}", path.display(), err)))
+}
+
+fn write_setting(path: &Path, value: &str) -> io::Result<()> {
+    fs::write(path, value)
+        .map_err(|err| io::Error::new(err.kind(), format!("Could not write {}: {}", path.display(), err)))
+}
@@ -0,0 +1,4 @@
+pub mod arp;
+pub mod forwarding;
+pub mod scheduler;
+pub mod utils;
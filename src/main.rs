@@ -1,25 +1,66 @@
+use std::time::Duration;
+
+use arprender::arp;
 use clap::Parser;
 use cli::{Args, Commands};
 
-pub mod arp;
-#[macro_use]
-pub mod commands;
-pub mod cli;
-#[macro_use]
-pub mod utils;
+mod cli;
+mod commands;
 
 fn main() {
     let args = Args::parse();
 
-    match args.cmd {
-        Commands::Interfaces => commands::interfaces(),
-        Commands::Scan { interface, timeout } => commands::scan(interface, timeout),
-        Commands::Resolve { interface, address, timeout } => commands::resolve(interface, address, timeout),
-        Commands::Impersonate {
+    let result = match args.cmd {
+        Commands::Interfaces { output } => commands::interfaces(output),
+        Commands::Scan {
+            interface,
+            targets,
+            exclude,
+            timeout,
+            rate,
+            retries,
+            retry_interval,
+            output,
+        } => {
+            let options = arp::ScanOptions {
+                timeout: Duration::from_secs(timeout.into()),
+                rate,
+                retries,
+                retry_interval: Duration::from_millis(retry_interval),
+            };
+
+            commands::scan(interface, targets, exclude, options, output)
+        }
+        Commands::Listen { interface, duration, output } => commands::listen(interface, duration, output),
+        Commands::Resolve { interface, address, timeout, mac, output } => commands::resolve(interface, address, timeout, mac, output),
+        Commands::Impersonate(args) => commands::impersonate(args),
+        Commands::Mitm {
+            victim,
+            gateway,
             interface,
-            target,
-            stealthy,
             period,
-        } => commands::impersonate(interface, target, stealthy, period),
+            timeout,
+            forward,
+            relay,
+        } => commands::mitm(interface, victim, gateway, period, timeout, forward, relay),
+        Commands::Heal { interface, targets, timeout } => commands::heal(interface, targets, timeout),
+        Commands::Announce {
+            address,
+            interface,
+            mac,
+            count,
+            interval,
+            reply,
+        } => commands::announce(interface, address, mac, count, interval, reply),
+        Commands::Probe {
+            address,
+            interface,
+            no_announce,
+        } => commands::probe(interface, address, no_announce),
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,238 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::arp::CancelHandle;
+
+/// The longest single sleep between two rounds, so that a stop request is noticed promptly.
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+/// The shortest time between the starts of two rounds, so that a zero interval cannot turn the attack into a flood.
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Settings which control how often an attack round is repeated and when the attack ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    /// The time from the start of one round to the start of the next. Shorter intervals than 10 milliseconds are
+    /// raised to it.
+    pub interval: Duration,
+    /// The upper bound of a random delay added to each interval, so that the rounds are less regular.
+    pub jitter: Duration,
+    /// The maximum number of rounds. `None` repeats the attack until it is stopped otherwise.
+    pub count: Option<u64>,
+    /// How long the attack may run for. `None` repeats the attack until it is stopped otherwise.
+    pub duration: Option<Duration>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            count: None,
+            duration: None,
+        }
+    }
+}
+
+/// Why a scheduled attack ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The maximum number of rounds was sent.
+    Count,
+    /// The maximum duration passed.
+    Duration,
+    /// The stop handle was cancelled, for example by a signal.
+    Cancelled,
+}
+
+/// A summary of a scheduled attack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleReport {
+    /// The number of rounds which were run.
+    pub rounds: u64,
+    /// The time from the start of the first round until the attack ended.
+    pub elapsed: Duration,
+    pub stop_reason: StopReason,
+}
+
+impl Schedule {
+    /// Runs `round` at every tick of the schedule until the count or duration is reached or `stop` is cancelled.
+    /// The thread sleeps between rounds instead of spinning.
+    ///
+    /// The round is given its zero-based index. The first error it returns ends the attack and is returned.
+    pub fn run<F, E>(&self, stop: &CancelHandle, mut round: F) -> Result<ScheduleReport, E>
+    where
+        F: FnMut(u64) -> Result<(), E>,
+    {
+        let start = Instant::now();
+        let deadline = self.duration.map(|duration| start + duration);
+        let mut rng = rand::thread_rng();
+        let mut rounds = 0;
+
+        let stop_reason = loop {
+            if stop.is_cancelled() {
+                break StopReason::Cancelled;
+            }
+
+            if self.count.is_some_and(|count| rounds >= count) {
+                break StopReason::Count;
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break StopReason::Duration;
+            }
+
+            let round_start = Instant::now();
+            round(rounds)?;
+            rounds += 1;
+
+            // There is no need to wait for a tick which will not be used
+            if self.count.is_some_and(|count| rounds >= count) {
+                break StopReason::Count;
+            }
+
+            let mut next_tick = round_start + self.interval.max(MIN_INTERVAL);
+            if !self.jitter.is_zero() {
+                next_tick += rng.gen_range(Duration::ZERO..=self.jitter);
+            }
+
+            // The attack ends at the deadline rather than at the tick after it
+            if let Some(deadline) = deadline {
+                next_tick = next_tick.min(deadline);
+            }
+
+            sleep_until(next_tick, stop);
+        };
+
+        Ok(ScheduleReport {
+            rounds,
+            elapsed: start.elapsed(),
+            stop_reason,
+        })
+    }
+}
+
+/// Sleeps until the given instant, waking up early if `stop` is cancelled.
+fn sleep_until(instant: Instant, stop: &CancelHandle) {
+    loop {
+        let now = Instant::now();
+        if now >= instant || stop.is_cancelled() {
+            return;
+        }
+
+        thread::sleep((instant - now).min(SLEEP_SLICE));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(interval: Duration, count: Option<u64>, duration: Option<Duration>) -> Schedule {
+        Schedule {
+            interval,
+            jitter: Duration::ZERO,
+            count,
+            duration,
+        }
+    }
+
+    fn run(schedule: &Schedule, stop: &CancelHandle) -> ScheduleReport {
+        schedule.run(stop, |_| Ok::<_, ()>(())).unwrap()
+    }
+
+    #[test]
+    fn stops_after_count() {
+        let mut indices = Vec::new();
+        let report = schedule(Duration::from_millis(50), Some(3), None)
+            .run(&CancelHandle::new(), |round| {
+                indices.push(round);
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+
+        assert_eq!(report.stop_reason, StopReason::Count);
+        assert_eq!(report.rounds, 3);
+        assert_eq!(indices, vec![0, 1, 2]);
+
+        // The last round is not followed by another interval
+        assert!(report.elapsed >= Duration::from_millis(100));
+        assert!(report.elapsed < Duration::from_millis(150));
+    }
+
+    #[test]
+    fn stops_at_duration_deadline() {
+        let report = run(
+            &schedule(Duration::from_secs(10), None, Some(Duration::from_millis(50))),
+            &CancelHandle::new(),
+        );
+
+        assert_eq!(report.stop_reason, StopReason::Duration);
+        assert_eq!(report.rounds, 1);
+        assert!(report.elapsed >= Duration::from_millis(50));
+        assert!(report.elapsed < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let stop = CancelHandle::new();
+
+        let report = schedule(Duration::from_millis(10), None, None)
+            .run(&stop, |round| {
+                if round == 1 {
+                    stop.cancel();
+                }
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+
+        assert_eq!(report.stop_reason, StopReason::Cancelled);
+        assert_eq!(report.rounds, 2);
+    }
+
+    #[test]
+    fn wakes_up_when_cancelled_while_sleeping() {
+        let stop = CancelHandle::new();
+        let canceller = stop.clone();
+        let thread = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            canceller.cancel();
+        });
+
+        let report = run(&schedule(Duration::from_secs(10), None, None), &stop);
+        thread.join().unwrap();
+
+        assert_eq!(report.stop_reason, StopReason::Cancelled);
+        assert_eq!(report.rounds, 1);
+        assert!(report.elapsed < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn does_not_start_when_already_cancelled() {
+        let stop = CancelHandle::new();
+        stop.cancel();
+
+        let report = run(&schedule(Duration::from_secs(1), None, None), &stop);
+
+        assert_eq!(report.stop_reason, StopReason::Cancelled);
+        assert_eq!(report.rounds, 0);
+    }
+
+    #[test]
+    fn zero_interval_is_raised_to_minimum() {
+        let report = run(&schedule(Duration::ZERO, Some(5), None), &CancelHandle::new());
+
+        assert_eq!(report.rounds, 5);
+        assert!(report.elapsed >= MIN_INTERVAL * 4);
+    }
+
+    #[test]
+    fn returns_first_error() {
+        let result = schedule(Duration::from_millis(10), Some(5), None)
+            .run(&CancelHandle::new(), |round| if round == 2 { Err(round) } else { Ok(()) });
+
+        assert_eq!(result, Err(2));
+    }
+}